@@ -0,0 +1,561 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use crate::emulator::PROGRAM_START_ADDR;
+use crate::instruction::Instruction;
+use crate::symbols::Symbols;
+
+/*
+    Two pass assembler for the mnemonics printed by the disassembler and
+    the debugger (Cowgod's, see Instruction's Display impl). The first pass
+    reads every line, works out its address and records labels. The second
+    evaluates operands, now that every label is known, and emits the bytes.
+
+        loop:                       ; a label, may share a line with code
+        SPEED = 4                   ; a constant, `SPEED equ 4` also works
+            ADD V0, SPEED
+            LD I, LONG big_table    ; XO-CHIP F000 nnnn
+            JP loop
+        player:
+            sprite "..####.."       ; one row per string, # or X is a set pixel
+            db 0x3C, 0b01111110, "text"
+            dw 0x1234
+            include "other.asm"     ; relative to the including file
+
+    Numbers are decimal, 0x hex or 0b binary, and operands may add and
+    subtract numbers, labels and constants. Mnemonics, registers and
+    directives are case insensitive, symbol names are not.
+*/
+
+const MAX_INCLUDE_DEPTH: usize = 16;
+// Constants defined in terms of each other deeper than this are assumed to be circular
+const MAX_CONSTANT_DEPTH: usize = 32;
+const MEMORY_SIZE: usize = 0x10000;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AsmError {
+    pub file: String,
+    // 0 when the error is not about any one line, such as a missing file
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.line == 0 {
+            write!(f, "{}: {}", self.file, self.message)
+        } else {
+            write!(f, "{}:{}: {}", self.file, self.line, self.message)
+        }
+    }
+}
+
+impl std::error::Error for AsmError {}
+
+pub struct Assembly {
+    // Loads at PROGRAM_START_ADDR
+    pub rom: Vec<u8>,
+    // Every label, for the debugger
+    pub symbols: Symbols,
+}
+
+#[derive(Debug, Clone)]
+struct Location {
+    file: String,
+    line: usize,
+}
+
+impl Location {
+    fn error(&self, message: impl Into<String>) -> AsmError {
+        AsmError { file: self.file.clone(), line: self.line, message: message.into() }
+    }
+}
+
+enum Statement {
+    Instruction { mnemonic: String, operands: Vec<String> },
+    // db, each operand is an expression or a quoted string
+    Bytes(Vec<String>),
+    Words(Vec<String>),
+    // Sprite rows are plain bitmaps, so they are converted straight away
+    Sprite(Vec<u8>),
+}
+
+struct Line {
+    location: Location,
+    statement: Statement,
+}
+
+enum Symbol {
+    Label(u16),
+    Constant { expr: String, location: Location },
+}
+
+enum Operand<'a> {
+    V(usize),
+    I,
+    // [I]
+    IndirectI,
+    Dt,
+    St,
+    K,
+    F,
+    Hf,
+    B,
+    R,
+    // LONG nnnn, the operand of F000
+    Long(&'a str),
+    Value(&'a str),
+}
+
+#[derive(Default)]
+struct Assembler {
+    lines: Vec<Line>,
+    symbols: HashMap<String, Symbol>,
+    // Constant names in the order they were defined, so errors come out in source order
+    constants: Vec<String>,
+    addr: usize,
+}
+
+// Assemble a source file and anything it includes
+pub fn assemble_file(path: &Path) -> Result<Assembly, AsmError> {
+    let mut assembler = Assembler::new();
+    assembler.include(path, None, 0)?;
+    assembler.finish()
+}
+
+// Assemble source held in memory. Includes are relative to the working directory
+pub fn assemble(source: &str) -> Result<Assembly, AsmError> {
+    let mut assembler = Assembler::new();
+    assembler.read("<input>", Path::new(""), source, 0)?;
+    assembler.finish()
+}
+
+impl Assembler {
+    fn new() -> Self {
+        Self { addr: PROGRAM_START_ADDR, ..Self::default() }
+    }
+
+    fn include(&mut self, path: &Path, from: Option<&Location>, depth: usize) -> Result<(), AsmError> {
+        let name = path.display().to_string();
+        let source = fs::read_to_string(path).map_err(|e| {
+            let message = format!("unable to read {}: {}", name, e);
+            match from {
+                Some(location) => location.error(message),
+                None => AsmError { file: name.clone(), line: 0, message },
+            }
+        })?;
+        let dir = path.parent().unwrap_or_else(|| Path::new(""));
+
+        self.read(&name, dir, &source, depth)
+    }
+
+    // First pass over one file
+    fn read(&mut self, name: &str, dir: &Path, source: &str, depth: usize) -> Result<(), AsmError> {
+        for (i, text) in source.lines().enumerate() {
+            let location = Location { file: String::from(name), line: i + 1 };
+            let mut text = strip_comment(text).trim();
+
+            while let Some((label, rest)) = split_label(text) {
+                let addr = self.addr as u16;
+                self.define(label, Symbol::Label(addr), &location)?;
+                text = rest.trim();
+            }
+            if text.is_empty() {
+                continue;
+            }
+
+            let (word, rest) = match text.split_once(char::is_whitespace) {
+                Some((word, rest)) => (word, rest.trim()),
+                None => (text, ""),
+            };
+
+            // NAME = value or NAME equ value
+            let constant = rest.strip_prefix('=').or_else(|| {
+                let (keyword, value) = rest.split_once(char::is_whitespace)?;
+                if keyword.eq_ignore_ascii_case("equ") { Some(value) } else { None }
+            });
+            if let Some(expr) = constant {
+                let symbol = Symbol::Constant { expr: String::from(expr.trim()), location: location.clone() };
+                self.define(word, symbol, &location)?;
+                self.constants.push(String::from(word));
+                continue;
+            }
+
+            let (statement, size) = match word.to_ascii_lowercase().as_str() {
+                "include" => {
+                    if depth >= MAX_INCLUDE_DEPTH {
+                        return Err(location.error("includes nested too deeply"));
+                    }
+                    let file = parse_string(rest).map_err(|e| location.error(e))?;
+                    self.include(&dir.join(file), Some(&location), depth + 1)?;
+                    continue;
+                }
+                "db" => {
+                    let operands = split_operands(rest).map_err(|e| location.error(e))?;
+                    let mut size = 0;
+                    for operand in &operands {
+                        size += if operand.starts_with('"') {
+                            parse_string(operand).map_err(|e| location.error(e))?.len()
+                        } else {
+                            1
+                        };
+                    }
+                    (Statement::Bytes(operands), size)
+                }
+                "dw" => {
+                    let operands = split_operands(rest).map_err(|e| location.error(e))?;
+                    let size = operands.len() * 2;
+                    (Statement::Words(operands), size)
+                }
+                "sprite" => {
+                    let mut bytes = Vec::new();
+                    for operand in split_operands(rest).map_err(|e| location.error(e))? {
+                        bytes.extend(parse_sprite_row(&operand).map_err(|e| location.error(e))?);
+                    }
+                    let size = bytes.len();
+                    (Statement::Sprite(bytes), size)
+                }
+                _ => {
+                    let mnemonic = word.to_ascii_uppercase();
+                    let operands = split_operands(rest).map_err(|e| location.error(e))?;
+                    let long = mnemonic == "LD" && operands.len() == 2 && matches!(parse_operand(&operands[1]), Operand::Long(_));
+                    let size = if long { 4 } else { 2 };
+                    (Statement::Instruction { mnemonic, operands }, size)
+                }
+            };
+
+            self.addr += size;
+            if self.addr > MEMORY_SIZE {
+                return Err(location.error("program does not fit in memory"));
+            }
+            self.lines.push(Line { location, statement });
+        }
+
+        Ok(())
+    }
+
+    fn define(&mut self, name: &str, symbol: Symbol, location: &Location) -> Result<(), AsmError> {
+        if !is_identifier(name) {
+            return Err(location.error(format!("invalid symbol name {}", name)));
+        }
+        if !matches!(parse_operand(name), Operand::Value(_)) {
+            return Err(location.error(format!("{} is a register name", name)));
+        }
+        if self.symbols.contains_key(name) {
+            return Err(location.error(format!("{} is already defined", name)));
+        }
+
+        self.symbols.insert(String::from(name), symbol);
+        Ok(())
+    }
+
+    // Second pass
+    fn finish(self) -> Result<Assembly, AsmError> {
+        // Check every constant where it is defined, even ones never used
+        for name in &self.constants {
+            if let Some(Symbol::Constant { expr, location }) = self.symbols.get(name) {
+                self.eval(expr, 0).map_err(|e| location.error(e))?;
+            }
+        }
+
+        let mut rom = Vec::new();
+        for line in &self.lines {
+            let bytes = self.emit(&line.statement).map_err(|e| line.location.error(e))?;
+            rom.extend(bytes);
+        }
+
+        let mut symbols = Symbols::new();
+        for (name, symbol) in &self.symbols {
+            if let Symbol::Label(addr) = symbol {
+                symbols.insert(name, *addr);
+            }
+        }
+
+        Ok(Assembly { rom, symbols })
+    }
+
+    fn emit(&self, statement: &Statement) -> Result<Vec<u8>, String> {
+        match statement {
+            Statement::Instruction { mnemonic, operands } => self.emit_instruction(mnemonic, operands),
+            Statement::Bytes(operands) => {
+                let mut bytes = Vec::new();
+                for operand in operands {
+                    if operand.starts_with('"') {
+                        bytes.extend(parse_string(operand)?.bytes());
+                    } else {
+                        bytes.push(self.byte(operand)?);
+                    }
+                }
+                Ok(bytes)
+            }
+            Statement::Words(operands) => {
+                let mut bytes = Vec::new();
+                for operand in operands {
+                    bytes.extend(self.word(operand)?.to_be_bytes());
+                }
+                Ok(bytes)
+            }
+            Statement::Sprite(bytes) => Ok(bytes.clone()),
+        }
+    }
+
+    fn emit_instruction(&self, mnemonic: &str, operands: &[String]) -> Result<Vec<u8>, String> {
+        use Operand::*;
+
+        let operands: Vec<Operand> = operands.iter().map(|operand| parse_operand(operand)).collect();
+
+        let instruction = match (mnemonic, operands.as_slice()) {
+            ("NOP", []) => Instruction::Nop,
+            ("SCD", [Value(n)]) => Instruction::ScrollDown { n: self.nibble(n)? },
+            ("CLS", []) => Instruction::Cls,
+            ("RET", []) => Instruction::Ret,
+            ("SCR", []) => Instruction::ScrollRight,
+            ("SCL", []) => Instruction::ScrollLeft,
+            ("EXIT", []) => Instruction::Exit,
+            ("LOW", []) => Instruction::LowRes,
+            ("HIGH", []) => Instruction::HighRes,
+            ("JP", [Value(nnn)]) => Instruction::Jump { nnn: self.address(nnn)? },
+            ("JP", [V(0), Value(nnn)]) => Instruction::JumpV0 { nnn: self.address(nnn)? },
+            ("CALL", [Value(nnn)]) => Instruction::Call { nnn: self.address(nnn)? },
+            ("SE", [V(x), V(y)]) => Instruction::SkipEqReg { x: *x, y: *y },
+            ("SE", [V(x), Value(nn)]) => Instruction::SkipEqImm { x: *x, nn: self.byte(nn)? },
+            ("SNE", [V(x), V(y)]) => Instruction::SkipNeReg { x: *x, y: *y },
+            ("SNE", [V(x), Value(nn)]) => Instruction::SkipNeImm { x: *x, nn: self.byte(nn)? },
+            ("SAVE", [V(x), V(y)]) => Instruction::SaveRange { x: *x, y: *y },
+            ("LOAD", [V(x), V(y)]) => Instruction::LoadRange { x: *x, y: *y },
+            ("LD", [V(x), V(y)]) => Instruction::LoadReg { x: *x, y: *y },
+            ("LD", [V(x), Value(nn)]) => Instruction::LoadImm { x: *x, nn: self.byte(nn)? },
+            ("LD", [I, Value(nnn)]) => Instruction::LoadIndex { nnn: self.address(nnn)? },
+            ("LD", [I, Long(nnnn)]) => {
+                let mut bytes = Instruction::LoadIndexLong.encode().to_be_bytes().to_vec();
+                bytes.extend(self.word(nnnn)?.to_be_bytes());
+                return Ok(bytes);
+            }
+            ("LD", [V(x), Dt]) => Instruction::LoadDelay { x: *x },
+            ("LD", [V(x), K]) => Instruction::WaitKey { x: *x },
+            ("LD", [Dt, V(x)]) => Instruction::SetDelay { x: *x },
+            ("LD", [St, V(x)]) => Instruction::SetSound { x: *x },
+            ("LD", [F, V(x)]) => Instruction::LoadFont { x: *x },
+            ("LD", [Hf, V(x)]) => Instruction::LoadBigFont { x: *x },
+            ("LD", [B, V(x)]) => Instruction::StoreBcd { x: *x },
+            ("LD", [IndirectI, V(x)]) => Instruction::StoreRegs { x: *x },
+            ("LD", [V(x), IndirectI]) => Instruction::LoadRegs { x: *x },
+            ("LD", [R, V(x)]) => Instruction::StoreFlags { x: *x },
+            ("LD", [V(x), R]) => Instruction::LoadFlags { x: *x },
+            ("ADD", [V(x), V(y)]) => Instruction::AddReg { x: *x, y: *y },
+            ("ADD", [V(x), Value(nn)]) => Instruction::AddImm { x: *x, nn: self.byte(nn)? },
+            ("ADD", [I, V(x)]) => Instruction::AddIndex { x: *x },
+            ("OR", [V(x), V(y)]) => Instruction::Or { x: *x, y: *y },
+            ("AND", [V(x), V(y)]) => Instruction::And { x: *x, y: *y },
+            ("XOR", [V(x), V(y)]) => Instruction::Xor { x: *x, y: *y },
+            ("SUB", [V(x), V(y)]) => Instruction::SubReg { x: *x, y: *y },
+            ("SUBN", [V(x), V(y)]) => Instruction::SubNReg { x: *x, y: *y },
+            // With one operand the shift reads and writes the same register
+            ("SHR", [V(x)]) => Instruction::ShiftRight { x: *x, y: *x },
+            ("SHR", [V(x), V(y)]) => Instruction::ShiftRight { x: *x, y: *y },
+            ("SHL", [V(x)]) => Instruction::ShiftLeft { x: *x, y: *x },
+            ("SHL", [V(x), V(y)]) => Instruction::ShiftLeft { x: *x, y: *y },
+            ("RND", [V(x), Value(nn)]) => Instruction::Random { x: *x, nn: self.byte(nn)? },
+            ("DRW", [V(x), V(y), Value(n)]) => Instruction::Draw { x: *x, y: *y, n: self.nibble(n)? },
+            ("SKP", [V(x)]) => Instruction::SkipKeyPressed { x: *x },
+            ("SKNP", [V(x)]) => Instruction::SkipKeyNotPressed { x: *x },
+            ("PLANE", [Value(n)]) => Instruction::SelectPlane { n: self.nibble(n)? },
+            ("AUDIO", []) => Instruction::LoadAudio,
+            ("PITCH", [V(x)]) => Instruction::SetPitch { x: *x },
+
+            (
+                "NOP" | "SCD" | "CLS" | "RET" | "SCR" | "SCL" | "EXIT" | "LOW" | "HIGH" | "JP" | "CALL" | "SE" | "SNE"
+                | "SAVE" | "LOAD" | "LD" | "ADD" | "OR" | "AND" | "XOR" | "SUB" | "SUBN" | "SHR" | "SHL" | "RND" | "DRW"
+                | "SKP" | "SKNP" | "PLANE" | "AUDIO" | "PITCH",
+                _,
+            ) => return Err(format!("invalid operands for {}", mnemonic)),
+            _ => return Err(format!("unknown instruction {}", mnemonic)),
+        };
+
+        Ok(instruction.encode().to_be_bytes().to_vec())
+    }
+
+    fn address(&self, expr: &str) -> Result<u16, String> {
+        self.value_in(expr, 0, 0xFFF).map(|value| value as u16)
+    }
+
+    // Negative bytes are allowed so `ADD V0, -1` works
+    fn byte(&self, expr: &str) -> Result<u8, String> {
+        self.value_in(expr, -0x80, 0xFF).map(|value| value as u8)
+    }
+
+    fn word(&self, expr: &str) -> Result<u16, String> {
+        self.value_in(expr, -0x8000, 0xFFFF).map(|value| value as u16)
+    }
+
+    fn nibble(&self, expr: &str) -> Result<u8, String> {
+        self.value_in(expr, 0, 0xF).map(|value| value as u8)
+    }
+
+    fn value_in(&self, expr: &str, min: i64, max: i64) -> Result<i64, String> {
+        let value = self.eval(expr, 0)?;
+        if value < min || value > max {
+            return Err(format!("{} is out of range, must be between {} and {}", expr, min, max));
+        }
+
+        Ok(value)
+    }
+
+    // Terms joined by + and -, with an optional leading minus
+    fn eval(&self, expr: &str, depth: usize) -> Result<i64, String> {
+        let mut rest = expr.trim();
+        let mut sign = 1;
+        if let Some(negated) = rest.strip_prefix('-') {
+            sign = -1;
+            rest = negated;
+        }
+
+        let mut total = 0;
+        loop {
+            let end = rest.find(['+', '-']).unwrap_or(rest.len());
+            total += sign * self.term(rest[..end].trim(), depth)?;
+            if end == rest.len() {
+                return Ok(total);
+            }
+
+            sign = if rest[end..].starts_with('-') { -1 } else { 1 };
+            rest = &rest[end + 1..];
+        }
+    }
+
+    fn term(&self, term: &str, depth: usize) -> Result<i64, String> {
+        if term.is_empty() {
+            return Err(String::from("missing value"));
+        }
+        if term.starts_with(|c: char| c.is_ascii_digit()) {
+            return parse_number(term).ok_or_else(|| format!("invalid number {}", term));
+        }
+
+        match self.symbols.get(term) {
+            Some(Symbol::Label(addr)) => Ok(*addr as i64),
+            Some(Symbol::Constant { .. }) if depth >= MAX_CONSTANT_DEPTH => {
+                Err(format!("{} is defined in terms of itself", term))
+            }
+            Some(Symbol::Constant { expr, .. }) => self.eval(expr, depth + 1),
+            None => Err(format!("undefined symbol {}", term)),
+        }
+    }
+}
+
+fn parse_operand(text: &str) -> Operand<'_> {
+    let upper = text.to_ascii_uppercase();
+
+    match upper.as_str() {
+        "I" => Operand::I,
+        "[I]" => Operand::IndirectI,
+        "DT" => Operand::Dt,
+        "ST" => Operand::St,
+        "K" => Operand::K,
+        "F" => Operand::F,
+        "HF" => Operand::Hf,
+        "B" => Operand::B,
+        "R" => Operand::R,
+        _ if upper.len() == 2 && upper.starts_with('V') && upper.as_bytes()[1].is_ascii_hexdigit() => {
+            Operand::V(usize::from_str_radix(&upper[1..], 16).unwrap())
+        }
+        _ if upper.starts_with("LONG ") => Operand::Long(text[5..].trim()),
+        _ => Operand::Value(text),
+    }
+}
+
+fn parse_number(text: &str) -> Option<i64> {
+    if let Some(hex) = text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        i64::from_str_radix(hex, 16).ok()
+    } else if let Some(binary) = text.strip_prefix("0b").or_else(|| text.strip_prefix("0B")) {
+        i64::from_str_radix(binary, 2).ok()
+    } else {
+        text.parse().ok()
+    }
+}
+
+// A row of 8 or 16 pixels, "#" or "X" for set and "." or " " for clear
+fn parse_sprite_row(text: &str) -> Result<Vec<u8>, String> {
+    let row = parse_string(text)?;
+    if row.len() != 8 && row.len() != 16 {
+        return Err(format!("sprite rows must be 8 or 16 pixels wide, {} is {}", text, row.len()));
+    }
+
+    let mut bits: u16 = 0;
+    for c in row.chars() {
+        bits <<= 1;
+        match c {
+            '#' | 'X' | 'x' => bits |= 1,
+            '.' | ' ' => (),
+            _ => return Err(format!("invalid sprite pixel '{}'", c)),
+        }
+    }
+
+    let bytes = bits.to_be_bytes();
+    Ok(if row.len() == 8 { vec![bytes[1]] } else { bytes.to_vec() })
+}
+
+fn parse_string(text: &str) -> Result<&str, String> {
+    match text.strip_prefix('"').and_then(|rest| rest.strip_suffix('"')) {
+        Some(string) if string.is_ascii() => Ok(string),
+        Some(_) => Err(format!("{} contains characters that are not ASCII", text)),
+        None => Err(format!("expected a quoted string, found {}", text)),
+    }
+}
+
+// Split on commas that are not inside a string
+fn split_operands(text: &str) -> Result<Vec<String>, String> {
+    if text.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut operands = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+    for c in text.chars() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                current.push(c);
+            }
+            ',' if !quoted => operands.push(std::mem::take(&mut current)),
+            _ => current.push(c),
+        }
+    }
+    operands.push(current);
+
+    let operands: Vec<String> = operands.iter().map(|operand| String::from(operand.trim())).collect();
+    if operands.iter().any(|operand| operand.is_empty()) {
+        return Err(String::from("missing operand"));
+    }
+
+    Ok(operands)
+}
+
+fn strip_comment(text: &str) -> &str {
+    let mut quoted = false;
+    for (i, c) in text.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            ';' if !quoted => return &text[..i],
+            _ => (),
+        }
+    }
+
+    text
+}
+
+// `name:` at the start of a line, returning the name and what follows it
+fn split_label(text: &str) -> Option<(&str, &str)> {
+    let (label, rest) = text.split_once(':')?;
+    if is_identifier(label) { Some((label, rest)) } else { None }
+}
+
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' => chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.'),
+        _ => false,
+    }
+}
@@ -0,0 +1,77 @@
+use chip8_core::asm::assemble_file;
+use chip8_core::octo::compile_file;
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+
+const USAGE: &str = "Usage: chip8-asm path/to/source.asm [-o output.ch8] [--symbols output.sym]
+
+Files ending in .8o are compiled as Octo instead. The ROM is written next to the source with a .ch8 extension unless -o is given.
+--symbols writes the labels for the emulator's debugger, see `--symbols` there.";
+
+struct Options {
+    source: PathBuf,
+    output: Option<PathBuf>,
+    symbols: Option<PathBuf>,
+}
+
+fn parse_args() -> Result<Options, String> {
+    let mut source = None;
+    let mut output = None;
+    let mut symbols = None;
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-o" => output = Some(PathBuf::from(args.next().ok_or("-o needs a file name")?)),
+            "--symbols" => symbols = Some(PathBuf::from(args.next().ok_or("--symbols needs a file name")?)),
+            _ if arg.starts_with('-') => return Err(format!("unknown option {}", arg)),
+            _ if source.is_none() => source = Some(PathBuf::from(arg)),
+            _ => return Err(String::from("only one source file can be given")),
+        }
+    }
+
+    let source = source.ok_or("no source file given")?;
+
+    Ok(Options { source, output, symbols })
+}
+
+fn main() {
+    let options = match parse_args() {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{}\n{}", e, USAGE);
+            process::exit(2);
+        }
+    };
+
+    if let Err(e) = run(&options) {
+        eprintln!("Error: {}", e);
+        process::exit(1);
+    }
+}
+
+fn run(options: &Options) -> Result<(), String> {
+    let assembly = if options.source.extension().is_some_and(|ext| ext == "8o") {
+        compile_file(&options.source)
+    } else {
+        assemble_file(&options.source)
+    };
+    let assembly = assembly.map_err(|e| e.to_string())?;
+
+    let output = options.output.clone().unwrap_or_else(|| options.source.with_extension("ch8"));
+    write(&output, &assembly.rom)?;
+
+    if let Some(path) = &options.symbols {
+        write(path, assembly.symbols.to_string().as_bytes())?;
+    }
+
+    println!("Wrote {} bytes to {}", assembly.rom.len(), output.display());
+    Ok(())
+}
+
+fn write(path: &Path, contents: &[u8]) -> Result<(), String> {
+    fs::write(path, contents).map_err(|e| format!("Unable to write {}: {}", path.display(), e))
+}
@@ -0,0 +1,42 @@
+use chip8_core::disasm::Disassembly;
+use chip8_core::emulator::PROGRAM_START_ADDR;
+
+use std::env;
+use std::fs;
+use std::process;
+
+const USAGE: &str = "Usage: chip8-disasm path/to/game [-o output.asm]";
+
+fn main() {
+    let args: Vec<_> = env::args().skip(1).collect();
+
+    let (rom_path, output) = match args.as_slice() {
+        [rom] => (rom, None),
+        [rom, flag, output] if flag == "-o" => (rom, Some(output)),
+        _ => {
+            eprintln!("{}", USAGE);
+            process::exit(2);
+        }
+    };
+
+    let rom = match fs::read(rom_path) {
+        Ok(rom) => rom,
+        Err(e) => {
+            eprintln!("Unable to open {}: {}", rom_path, e);
+            process::exit(1);
+        }
+    };
+
+    let disassembly = Disassembly::new(&rom, PROGRAM_START_ADDR as u16);
+    let listing = format!("; Disassembly of {}\n\n{}", rom_path, disassembly.listing());
+
+    match output {
+        Some(path) => {
+            if let Err(e) = fs::write(path, listing) {
+                eprintln!("Unable to write {}: {}", path, e);
+                process::exit(1);
+            }
+        }
+        None => print!("{}", listing),
+    }
+}
@@ -0,0 +1,273 @@
+use chip8_core::emulator::Emulator;
+use chip8_core::host::{random_seed, RandomMode, StderrLog};
+use chip8_core::image::{encode_pbm, encode_png};
+use chip8_core::octo;
+use chip8_core::quirks::Quirks;
+use chip8_core::sound::{Renderer, Wave, DEFAULT_FREQUENCY};
+use chip8_core::timing::Timing;
+use chip8_core::wav::encode_wav;
+
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::process;
+
+/*
+    Runs a ROM without a window for a fixed number of frames, then writes
+    the screen and the machine state out so tests can check them. Frames
+    are run exactly like the SDL frontend runs them.
+*/
+
+const DEFAULT_FRAMES: usize = 60;
+const NUM_KEYS: usize = 16;
+const DEFAULT_SAMPLE_RATE: u32 = 44100;
+// Grey level for each combination of XO-CHIP planes, the same as the SDL palette
+const GREYS: [u8; 4] = [0, 255, 170, 85];
+
+const USAGE: &str = "Usage: chip8-headless [options] path/to/game
+
+Options:
+    --quirks <profile>     modern, vip, chip48, schip or xochip (default modern)
+    --seed <number>        seed for Cxnn's random numbers (default: a new one every run)
+    --random <mode>        xorshift or vip (default xorshift)
+    --timing <mode>        fixed or vip (default fixed)
+    --frames <count>       frames to run at 60 per second (default 60)
+    --keys <script>        key presses, see below, or @file to read them from a file
+    --screen <file>        write the final screen, as PNG or as PBM if the name ends in .pbm
+    --registers <file>     write the registers as JSON, - for standard output
+    --wav <file>           record the buzzer as a WAV file, beeps as a 440 Hz square wave
+    --sample-rate <hz>     sample rate of the recording (default 44100)
+
+A key script is a list of events separated by commas or whitespace. 10+5
+presses key 5 before frame 10 runs and 20-5 releases it before frame 20.
+Keys are hex digits.
+
+The exit code is 1 if the ROM fails with an error, after the outputs are
+written, and 2 if the arguments are wrong.";
+
+struct Options {
+    rom: String,
+    quirks: Quirks,
+    seed: Option<u64>,
+    random: RandomMode,
+    timing: Timing,
+    frames: usize,
+    keys: Vec<KeyEvent>,
+    screen: Option<String>,
+    registers: Option<String>,
+    wav: Option<String>,
+    sample_rate: u32,
+}
+
+struct KeyEvent {
+    frame: usize,
+    key: usize,
+    pressed: bool,
+}
+
+fn parse_args() -> Result<Options, String> {
+    let mut rom = None;
+    let mut quirks = Quirks::default();
+    let mut seed = None;
+    let mut random = RandomMode::Xorshift;
+    let mut timing = Timing::Fixed;
+    let mut frames = DEFAULT_FRAMES;
+    let mut keys = Vec::new();
+    let mut screen = None;
+    let mut registers = None;
+    let mut wav = None;
+    let mut sample_rate = DEFAULT_SAMPLE_RATE;
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--quirks" => {
+                let name = args.next().ok_or("--quirks needs a profile name")?;
+                quirks = name.parse()?;
+            }
+            "--seed" => {
+                let number = args.next().ok_or("--seed needs a number")?;
+                seed = Some(parse_seed(&number)?);
+            }
+            "--random" => {
+                let name = args.next().ok_or("--random needs a mode")?;
+                random = name.parse()?;
+            }
+            "--timing" => {
+                let name = args.next().ok_or("--timing needs a mode")?;
+                timing = name.parse()?;
+            }
+            "--frames" => {
+                let count = args.next().ok_or("--frames needs a count")?;
+                frames = count.parse().map_err(|_| format!("invalid frame count {}", count))?;
+            }
+            "--keys" => {
+                let script = args.next().ok_or("--keys needs a script")?;
+                let script = match script.strip_prefix('@') {
+                    Some(path) => fs::read_to_string(path).map_err(|e| format!("Unable to open {}: {}", path, e))?,
+                    None => script,
+                };
+                keys = parse_key_script(&script)?;
+            }
+            "--screen" => screen = Some(args.next().ok_or("--screen needs a file name")?),
+            "--registers" => registers = Some(args.next().ok_or("--registers needs a file name")?),
+            "--wav" => wav = Some(args.next().ok_or("--wav needs a file name")?),
+            "--sample-rate" => {
+                let hz = args.next().ok_or("--sample-rate needs a rate")?;
+                sample_rate = match hz.parse() {
+                    Ok(hz) if hz > 0 => hz,
+                    _ => return Err(format!("invalid sample rate {}", hz)),
+                };
+            }
+            _ if arg.starts_with("--") => return Err(format!("unknown option {}", arg)),
+            _ if rom.is_none() => rom = Some(arg),
+            _ => return Err(String::from("only one ROM can be given")),
+        }
+    }
+
+    let rom = rom.ok_or("no ROM given")?;
+
+    Ok(Options { rom, quirks, seed, random, timing, frames, keys, screen, registers, wav, sample_rate })
+}
+
+fn parse_key_script(script: &str) -> Result<Vec<KeyEvent>, String> {
+    let mut events = Vec::new();
+
+    for event in script.split(|c: char| c == ',' || c.is_whitespace()).filter(|event| !event.is_empty()) {
+        let (at, pressed) = match event.find(['+', '-']) {
+            Some(at) => (at, event[at..].starts_with('+')),
+            None => return Err(format!("invalid key event {}, expected <frame>+<key> or <frame>-<key>", event)),
+        };
+
+        let frame = event[..at].parse().map_err(|_| format!("invalid frame in key event {}", event))?;
+        let key = match usize::from_str_radix(&event[at + 1..], 16) {
+            Ok(key) if key < NUM_KEYS => key,
+            _ => return Err(format!("invalid key in key event {}", event)),
+        };
+
+        events.push(KeyEvent { frame, key, pressed });
+    }
+
+    Ok(events)
+}
+
+// Decimal, or hex with a leading 0x
+fn parse_seed(text: &str) -> Result<u64, String> {
+    let seed = match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        Some(hex) => u64::from_str_radix(hex, 16),
+        None => text.parse(),
+    };
+    seed.map_err(|_| format!("invalid seed {}", text))
+}
+
+fn main() {
+    let options = match parse_args() {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{}\n{}", e, USAGE);
+            process::exit(2);
+        }
+    };
+
+    match run(&options) {
+        Ok(true) => (),
+        Ok(false) => process::exit(1),
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            process::exit(1);
+        }
+    }
+}
+
+// Returns false if the ROM stopped with an error
+fn run(options: &Options) -> Result<bool, String> {
+    let path = &options.rom;
+    let rom = if path.ends_with(".8o") {
+        octo::compile_file(Path::new(path)).map_err(|e| e.to_string())?.rom
+    } else {
+        fs::read(path).map_err(|e| format!("Unable to open {}: {}", path, e))?
+    };
+
+    let mut chip8 = Emulator::new(options.quirks);
+    chip8.set_log(Box::new(StderrLog));
+    chip8.set_timing(options.timing);
+    let seed = options.seed.unwrap_or_else(random_seed);
+    chip8.set_random_source(options.random.source(seed));
+    chip8.load_rom(&rom).map_err(|e| e.to_string())?;
+
+    let mut renderer = Renderer::new(options.sample_rate, Wave::Square, DEFAULT_FREQUENCY);
+    let mut samples = Vec::new();
+
+    let mut frames = 0;
+    let mut ok = true;
+    while frames < options.frames && !chip8.is_halted() {
+        for event in options.keys.iter().filter(|event| event.frame == frames) {
+            chip8.key_press(event.key, event.pressed);
+        }
+
+        // The SDL frontend also sets the tone before running each frame
+        if options.wav.is_some() {
+            renderer.render_frame(chip8.tone(), &mut samples);
+        }
+
+        if let Err(e) = chip8.run_frame() {
+            eprintln!("Error in frame {}: {}", frames, e);
+            ok = false;
+            break;
+        }
+        frames += 1;
+    }
+
+    if let Some(path) = &options.screen {
+        write_screen(&chip8, path)?;
+    }
+    if let Some(path) = &options.wav {
+        fs::write(path, encode_wav(options.sample_rate, &samples)).map_err(|e| format!("Unable to write {}: {}", path, e))?;
+    }
+    if let Some(path) = &options.registers {
+        let json = registers_json(&chip8, frames, seed);
+        if path == "-" {
+            println!("{}", json);
+        } else {
+            fs::write(path, json + "\n").map_err(|e| format!("Unable to write {}: {}", path, e))?;
+        }
+    }
+
+    Ok(ok)
+}
+
+fn write_screen(emu: &Emulator, path: &str) -> Result<(), String> {
+    let (width, height) = (emu.screen_width(), emu.screen_height());
+    let screen = emu.get_screen();
+
+    let image = if path.ends_with(".pbm") {
+        let lit: Vec<bool> = screen.iter().map(|pixel| *pixel != 0).collect();
+        encode_pbm(width, height, &lit)
+    } else {
+        let greys: Vec<u8> = screen.iter().map(|pixel| GREYS[(*pixel & 0x3) as usize]).collect();
+        encode_png(width, height, &greys)
+    };
+
+    fs::write(path, image).map_err(|e| format!("Unable to write {}: {}", path, e))
+}
+
+fn registers_json(emu: &Emulator, frames: usize, seed: u64) -> String {
+    let list = |values: Vec<String>| format!("[{}]", values.join(", "));
+
+    let fields = [
+        format!("\"frames\": {}", frames),
+        format!("\"seed\": {}", seed),
+        format!("\"pc\": {}", emu.pc()),
+        format!("\"i\": {}", emu.index()),
+        format!("\"sp\": {}", emu.sp()),
+        format!("\"v\": {}", list(emu.registers().iter().map(|v| v.to_string()).collect())),
+        format!("\"stack\": {}", list(emu.stack().iter().map(|addr| addr.to_string()).collect())),
+        format!("\"delay_timer\": {}", emu.delay_timer()),
+        format!("\"sound_timer\": {}", emu.sound_timer()),
+        format!("\"width\": {}", emu.screen_width()),
+        format!("\"height\": {}", emu.screen_height()),
+        format!("\"halted\": {}", emu.is_halted()),
+    ];
+
+    format!("{{\n  {}\n}}", fields.join(",\n  "))
+}
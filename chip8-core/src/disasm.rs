@@ -0,0 +1,265 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use crate::instruction::{decode, Instruction};
+
+/*
+    Recursive-descent disassembler.
+
+    Starting from the entry point we follow every path the program can
+    take: fall through to the next instruction, both sides of a skip, jump
+    and call targets. Anything never reached this way is assumed to be
+    data, which for CHIP-8 programs is mostly sprites. Decoding goes
+    through the same decode() as the interpreter so the two always agree.
+
+    The listing uses the same mnemonics as Instruction's Display impl, with
+    jump, call and I targets replaced by labels. Targets that no line of the
+    listing starts at, outside the ROM or inside another instruction, are
+    defined as constants at the top so the listing assembles again.
+*/
+
+// Column the address/bytes comment starts in
+const COMMENT_COLUMN: usize = 32;
+// Bytes per `db` line for data nobody points I at
+const DATA_BYTES_PER_LINE: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Byte {
+    Data,
+    // First byte of an instruction
+    Code,
+    // Any later byte of an instruction
+    Operand,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum LabelKind {
+    // Ordered so a call target wins over a jump target, which wins over data
+    Data,
+    Jump,
+    Subroutine,
+}
+
+pub struct Disassembly<'a> {
+    rom: &'a [u8],
+    origin: u16,
+    bytes: Vec<Byte>,
+    labels: BTreeMap<u16, LabelKind>,
+}
+
+impl<'a> Disassembly<'a> {
+    // `origin` is the address the ROM is loaded at, normally 0x200
+    pub fn new(rom: &'a [u8], origin: u16) -> Self {
+        let mut disassembly = Self {
+            rom,
+            origin,
+            bytes: vec![Byte::Data; rom.len()],
+            labels: BTreeMap::new(),
+        };
+
+        disassembly.trace(origin);
+
+        disassembly
+    }
+
+    pub fn is_code(&self, addr: u16) -> bool {
+        self.offset(addr).is_some_and(|i| self.bytes[i] == Byte::Code)
+    }
+
+    // Name of the label at `addr`, if anything refers to it
+    pub fn label(&self, addr: u16) -> Option<String> {
+        self.labels.get(&addr).map(|kind| label_name(*kind, addr))
+    }
+
+    // Every label with its address, in address order
+    pub fn labels(&self) -> Vec<(String, u16)> {
+        self.labels.iter().map(|(addr, kind)| (label_name(*kind, *addr), *addr)).collect()
+    }
+
+    fn offset(&self, addr: u16) -> Option<usize> {
+        let offset = (addr as usize).checked_sub(self.origin as usize)?;
+        if offset < self.rom.len() { Some(offset) } else { None }
+    }
+
+    fn word(&self, addr: u16) -> Option<u16> {
+        let offset = self.offset(addr)?;
+        let hi = *self.rom.get(offset)? as u16;
+        let lo = *self.rom.get(offset + 1)? as u16;
+
+        Some((hi << 8) | lo)
+    }
+
+    fn add_label(&mut self, addr: u16, kind: LabelKind) {
+        let label = self.labels.entry(addr).or_insert(kind);
+        *label = (*label).max(kind);
+    }
+
+    fn trace(&mut self, entry: u16) {
+        let mut pending = vec![entry];
+
+        while let Some(addr) = pending.pop() {
+            // Stop at anything already traced, outside the ROM or overlapping another instruction
+            let offset = match self.offset(addr) {
+                Some(offset) if self.bytes[offset] == Byte::Data => offset,
+                _ => continue,
+            };
+            let instruction = match self.word(addr).map(decode) {
+                Some(Ok(instruction)) => instruction,
+                _ => continue,
+            };
+
+            let len = if instruction == Instruction::LoadIndexLong { 4 } else { 2 };
+            if offset + len > self.rom.len() || self.bytes[offset + 1..offset + len].iter().any(|b| *b != Byte::Data) {
+                continue;
+            }
+            self.bytes[offset] = Byte::Code;
+            for byte in &mut self.bytes[offset + 1..offset + len] {
+                *byte = Byte::Operand;
+            }
+
+            let next = addr.wrapping_add(len as u16);
+            match instruction {
+                Instruction::Ret | Instruction::Exit => (),
+                Instruction::Jump { nnn } => {
+                    self.add_label(nnn, LabelKind::Jump);
+                    pending.push(nnn);
+                }
+                Instruction::Call { nnn } => {
+                    self.add_label(nnn, LabelKind::Subroutine);
+                    pending.push(nnn);
+                    pending.push(next);
+                }
+                // The target is computed at run time, but the base is usually a table of jumps
+                Instruction::JumpV0 { nnn } => {
+                    self.add_label(nnn, LabelKind::Jump);
+                    pending.push(nnn);
+                }
+                Instruction::SkipEqImm { .. }
+                | Instruction::SkipNeImm { .. }
+                | Instruction::SkipEqReg { .. }
+                | Instruction::SkipNeReg { .. }
+                | Instruction::SkipKeyPressed { .. }
+                | Instruction::SkipKeyNotPressed { .. } => {
+                    // Skipping over F000 nnnn skips four bytes
+                    let skipped = if self.word(next) == Some(0xF000) { 4 } else { 2 };
+                    pending.push(next);
+                    pending.push(next.wrapping_add(skipped));
+                }
+                Instruction::LoadIndex { nnn } => {
+                    self.add_label(nnn, LabelKind::Data);
+                    pending.push(next);
+                }
+                Instruction::LoadIndexLong => {
+                    if let Some(target) = self.word(addr + 2) {
+                        self.add_label(target, LabelKind::Data);
+                    }
+                    pending.push(next);
+                }
+                _ => pending.push(next),
+            }
+        }
+    }
+
+    // Render an instruction with any address operand replaced by its label
+    fn format_instruction(&self, addr: u16, instruction: Instruction) -> String {
+        let target = |nnn: u16| self.label(nnn).unwrap_or_else(|| format!("0x{:03X}", nnn));
+
+        match instruction {
+            Instruction::Jump { nnn } => format!("JP {}", target(nnn)),
+            Instruction::Call { nnn } => format!("CALL {}", target(nnn)),
+            Instruction::JumpV0 { nnn } => format!("JP V0, {}", target(nnn)),
+            Instruction::LoadIndex { nnn } => format!("LD I, {}", target(nnn)),
+            Instruction::LoadIndexLong => {
+                let nnnn = self.word(addr + 2).unwrap_or(0);
+                let operand = self.label(nnnn).unwrap_or_else(|| format!("0x{:04X}", nnnn));
+                format!("LD I, LONG {}", operand)
+            }
+            _ => instruction.to_string(),
+        }
+    }
+
+    // The full assembly listing. Every line carries its address and raw bytes in a comment
+    pub fn listing(&self) -> String {
+        let mut out = String::new();
+        let mut defined = Vec::new();
+        let mut offset = 0;
+        // Data after a label that I is pointed at is drawn as sprites until the next code
+        let mut sprite = false;
+
+        while offset < self.rom.len() {
+            let addr = self.origin + offset as u16;
+
+            if let Some(label) = self.label(addr) {
+                writeln!(out, "{}:", label).unwrap();
+                defined.push(addr);
+            }
+
+            if self.bytes[offset] != Byte::Data {
+                sprite = false;
+            } else if self.labels.get(&addr) == Some(&LabelKind::Data) {
+                sprite = true;
+            }
+
+            let (text, len) = match self.bytes[offset] {
+                Byte::Code => {
+                    let instruction = decode(self.word(addr).unwrap()).unwrap();
+                    let len = if instruction == Instruction::LoadIndexLong { 4 } else { 2 };
+                    (self.format_instruction(addr, instruction), len)
+                }
+                // Sprites get one row per line so the bitmap comment lines up
+                _ if sprite => {
+                    let byte = self.rom[offset];
+                    (format!("db 0b{:08b}", byte), 1)
+                }
+                _ => {
+                    let len = self.data_run(offset).min(DATA_BYTES_PER_LINE);
+                    let bytes: Vec<String> = self.rom[offset..offset + len].iter().map(|b| format!("0x{:02X}", b)).collect();
+                    (format!("db {}", bytes.join(", ")), len)
+                }
+            };
+
+            let raw: Vec<String> = self.rom[offset..offset + len].iter().map(|b| format!("{:02X}", b)).collect();
+            let mut comment = format!("; {:04X}  {}", addr, raw.join(" "));
+            if text.starts_with("db 0b") {
+                let bitmap: String = (0..8).map(|bit| if self.rom[offset] & (0x80 >> bit) != 0 { '#' } else { '.' }).collect();
+                write!(comment, "  {}", bitmap).unwrap();
+            }
+
+            writeln!(out, "    {:<width$}{}", text, comment, width = COMMENT_COLUMN - 4).unwrap();
+            offset += len;
+        }
+
+        let mut equates = String::new();
+        for (name, addr) in self.labels() {
+            if !defined.contains(&addr) {
+                writeln!(equates, "{} = 0x{:03X}", name, addr).unwrap();
+            }
+        }
+        if !equates.is_empty() {
+            equates.push('\n');
+        }
+
+        equates + &out
+    }
+
+    // Number of data bytes from `offset` up to the next code byte or label
+    fn data_run(&self, offset: usize) -> usize {
+        let mut len = 1;
+        while offset + len < self.rom.len()
+            && self.bytes[offset + len] == Byte::Data
+            && !self.labels.contains_key(&(self.origin + (offset + len) as u16))
+        {
+            len += 1;
+        }
+
+        len
+    }
+}
+
+fn label_name(kind: LabelKind, addr: u16) -> String {
+    match kind {
+        LabelKind::Subroutine => format!("sub_{:03X}", addr),
+        LabelKind::Jump => format!("lbl_{:03X}", addr),
+        LabelKind::Data => format!("data_{:03X}", addr),
+    }
+}
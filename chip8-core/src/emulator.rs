@@ -0,0 +1,979 @@
+use alloc::boxed::Box;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::error::EmulatorError;
+use crate::host::{Log, RandomSource, Xorshift};
+use crate::instruction::{decode, Instruction};
+use crate::quirks::{IndexIncrement, Quirks};
+use crate::sound::{pattern_rate, Tone, PATTERN_BITS};
+use crate::state::{StateReader, StateWriter};
+use crate::timing::{self, Timing, CPU_CYCLES_PER_FRAME, SKIP_CYCLES};
+use crate::watch::{WatchHit, Watchpoint};
+
+// The screen buffer is sized for SUPER-CHIP's extended mode, in low
+// resolution mode only the top-left 64x32 of it is in use
+pub const SCREEN_WIDTH: usize     =  128;
+pub const SCREEN_HEIGHT: usize    =  64;
+pub const LORES_WIDTH: usize      =  64;
+pub const LORES_HEIGHT: usize     =  32;
+pub const PROGRAM_START_ADDR: usize = 0x200;
+// Instructions run per 60 Hz timer tick, unless the timing is Timing::Vip
+pub const TICKS_PER_FRAME: usize = 10;
+const NUM_V_REG: usize        =  16;
+const NUM_KEYS: usize         =  16;
+const RAM_SIZE: usize         =  4096;
+const XO_RAM_SIZE: usize      =  65536;
+const STACK_SIZE: usize       =  16;
+const FONTSET_SIZE: usize     =  80;
+const BIG_FONTSET_ADDR: usize =  FONTSET_SIZE;
+const BIG_FONTSET_SIZE: usize =  160;
+const NUM_RPL_FLAGS: usize    =  16;
+const AUDIO_PATTERN_SIZE: usize = PATTERN_BITS / 8;
+const DEFAULT_PITCH: u8       =  64;
+const NUM_PLANES: usize       =  2;
+
+const FONTSET: [u8; FONTSET_SIZE] = [
+    0xF0, 0x90, 0x90, 0x90, 0xF0, // 0 
+    0x20, 0x60, 0x20, 0x20, 0x70, // 1
+    0xF0, 0x10, 0xF0, 0x80, 0xF0, // 2
+    0xF0, 0x10, 0xF0, 0x10, 0xF0, // 3
+    0x90, 0x90, 0xF0, 0x10, 0x10, // 4
+    0xF0, 0x80, 0xF0, 0x10, 0xF0, // 5
+    0xF0, 0x80, 0xF0, 0x90, 0xF0, // 6
+    0xF0, 0x10, 0x20, 0x40, 0x40, // 7
+    0xF0, 0x90, 0xF0, 0x90, 0xF0, // 8
+    0xF0, 0x90, 0xF0, 0x10, 0xF0, // 9
+    0xF0, 0x90, 0xF0, 0x90, 0x90, // A
+    0xE0, 0x90, 0xE0, 0x90, 0xE0, // B
+    0xF0, 0x80, 0x80, 0x80, 0xF0, // C
+    0xE0, 0x90, 0x90, 0x90, 0xE0, // D
+    0xF0, 0x80, 0xF0, 0x80, 0xF0, // E
+    0xF0, 0x80, 0xF0, 0x80, 0x80, // F
+];
+
+// SUPER-CHIP's 8x10 digits. The original only had 0-9, A-F follow Octo
+const BIG_FONTSET: [u8; BIG_FONTSET_SIZE] = [
+    0xFF, 0xFF, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, // 0
+    0x18, 0x78, 0x78, 0x18, 0x18, 0x18, 0x18, 0x18, 0xFF, 0xFF, // 1
+    0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, // 2
+    0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, // 3
+    0xC3, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, 0x03, 0x03, 0x03, 0x03, // 4
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, // 5
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, // 6
+    0xFF, 0xFF, 0x03, 0x03, 0x06, 0x0C, 0x18, 0x18, 0x18, 0x18, // 7
+    0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, // 8
+    0xFF, 0xFF, 0xC3, 0xC3, 0xFF, 0xFF, 0x03, 0x03, 0xFF, 0xFF, // 9
+    0x7E, 0xFF, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, 0xC3, 0xC3, 0xC3, // A
+    0xFC, 0xFC, 0xC3, 0xC3, 0xFC, 0xFC, 0xC3, 0xC3, 0xFC, 0xFC, // B
+    0x3C, 0xFF, 0xC3, 0xC0, 0xC0, 0xC0, 0xC0, 0xC3, 0xFF, 0x3C, // C
+    0xFC, 0xFE, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xFE, 0xFC, // D
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, // E
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC0, 0xC0, 0xC0, 0xC0, // F
+];
+
+pub struct Emulator {
+    pc: u16,
+    sp: u16,
+    // memory_size() bytes. It and the screen are on the heap, microcontrollers
+    // have far less stack than XO-CHIP's 64K
+    ram: Vec<u8>,
+    v: [u8; NUM_V_REG],
+    // Each pixel holds one bit per bitplane, plain CHIP-8 only ever uses plane 1
+    screen: Vec<u8>,
+    index: u16,
+    stack: [u16; STACK_SIZE],
+    keys: [bool; NUM_KEYS],
+    dt: u8,
+    st: u8,
+    // SUPER-CHIP state: 128x64 mode, the HP48 RPL user flags and 00FD
+    hires: bool,
+    rpl: [u8; NUM_RPL_FLAGS],
+    halted: bool,
+    // XO-CHIP state: bitplanes selected by Fn01 and the audio pattern buffer
+    planes: u8,
+    audio_pattern: [u8; AUDIO_PATTERN_SIZE],
+    pitch: u8,
+    // Behind Cxnn. Its state goes into save states
+    random: Box<dyn RandomSource>,
+    log: Option<Box<dyn Log>>,
+    quirks: Quirks,
+    // With VIP timing, the machine cycles left before the next display
+    // interrupt, negative if the last instruction ran past it. Dxyn sets
+    // display_wait to give up the rest of the frame, and draws once the
+    // interrupt has set display_ready
+    timing: Timing,
+    frame_cycles: i32,
+    display_wait: bool,
+    display_ready: bool,
+    // Debugging aids, see watch.rs. `current` is the instruction being executed
+    watchpoints: Vec<Watchpoint>,
+    watch_hits: Vec<WatchHit>,
+    current: (u16, Instruction),
+}
+
+impl Emulator {
+    pub fn new(quirks: Quirks) -> Self {
+       let mut emu = Self {
+            pc: PROGRAM_START_ADDR as u16,
+            ram: vec![0; if quirks.xo_chip { XO_RAM_SIZE } else { RAM_SIZE }],
+            stack: [0; STACK_SIZE],
+            v: [0; NUM_V_REG],
+            keys: [false; NUM_KEYS],
+            screen: vec![0; SCREEN_WIDTH * SCREEN_HEIGHT],
+            index: 0,
+            sp: 0,
+            dt: 0,
+            st: 0,
+            hires: false,
+            rpl: [0; NUM_RPL_FLAGS],
+            halted: false,
+            planes: 1,
+            audio_pattern: [0; AUDIO_PATTERN_SIZE],
+            pitch: DEFAULT_PITCH,
+            random: Box::new(Xorshift::new(default_seed())),
+            log: None,
+            quirks,
+            timing: Timing::Fixed,
+            frame_cycles: CPU_CYCLES_PER_FRAME as i32,
+            display_wait: false,
+            display_ready: false,
+            watchpoints: Vec::new(),
+            watch_hits: Vec::new(),
+            current: (PROGRAM_START_ADDR as u16, Instruction::Nop),
+        };
+
+        emu.load_fonts();
+
+        emu
+    }
+
+    pub fn reset(&mut self) {
+        self.pc = PROGRAM_START_ADDR as u16;
+        self.ram.fill(0);
+        self.stack = [0; STACK_SIZE];
+        self.screen.fill(0);
+        self.v = [0; NUM_V_REG];
+        self.index = 0;
+        self.sp = 0;
+        self.dt = 0;
+        self.st = 0;
+        self.keys = [false; NUM_KEYS];
+        self.hires = false;
+        self.halted = false;
+        self.planes = 1;
+        self.audio_pattern = [0; AUDIO_PATTERN_SIZE];
+        self.pitch = DEFAULT_PITCH;
+        self.frame_cycles = CPU_CYCLES_PER_FRAME as i32;
+        self.display_wait = false;
+        self.display_ready = false;
+        // The RPL flags live outside the machine's RAM, so like on the HP48 they survive a reset
+        self.load_fonts();
+    }
+
+    fn load_fonts(&mut self) {
+        self.ram[0..FONTSET_SIZE].copy_from_slice(&FONTSET);
+        self.ram[BIG_FONTSET_ADDR..BIG_FONTSET_ADDR + BIG_FONTSET_SIZE].copy_from_slice(&BIG_FONTSET);
+    }
+
+    pub fn cycle(&mut self) -> Result<(), EmulatorError> {
+        // 00FD stops the interpreter for good
+        if self.halted {
+            return Ok(());
+        }
+
+        // Fetch -> Decode -> Execute
+        let addr = self.pc;
+        let op = self.fetch()?;
+
+        let instruction = decode(op).map_err(|_| EmulatorError::UnknownOpcode { addr, opcode: op })?;
+        self.current = (addr, instruction);
+
+        if self.timing == Timing::Vip {
+            // The VIP's Dxyn waits for the display interrupt before it draws
+            if let Instruction::Draw { .. } = instruction {
+                if !self.display_ready {
+                    self.pc = addr;
+                    self.display_wait = true;
+                    return Ok(());
+                }
+                self.display_ready = false;
+            }
+
+            self.frame_cycles -= timing::vip_cycles(instruction, &self.v) as i32;
+        }
+
+        if self.watchpoints.is_empty() {
+            return self.execute(instruction);
+        }
+
+        // Compare registers before and after so register watchpoints see every change
+        let (v, index) = (self.v, self.index);
+        let result = self.execute(instruction);
+
+        for i in 0..self.watchpoints.len() {
+            let watchpoint = self.watchpoints[i];
+            match watchpoint {
+                Watchpoint::Register(x) if v[x] != self.v[x] => {
+                    self.watch_hit(watchpoint, v[x] as u16, self.v[x] as u16);
+                }
+                Watchpoint::Index if index != self.index => {
+                    self.watch_hit(watchpoint, index, self.index);
+                }
+                _ => (),
+            }
+        }
+
+        result
+    }
+
+    pub fn fetch(&mut self) -> Result<u16, EmulatorError> {
+        let hi_byte = self.read(self.pc as usize)? as u16;
+        let lo_byte = self.read(self.pc as usize + 1)? as u16;
+
+        let opcode = (hi_byte << 8) | lo_byte;
+        // XO-CHIP's 64K reaches the top of the address space, where the program counter wraps to 0
+        self.pc = self.pc.wrapping_add(2);
+
+        Ok(opcode)
+    }
+
+    pub fn execute(&mut self, instruction: Instruction) -> Result<(), EmulatorError> {
+        match instruction {
+            Instruction::Nop => (),
+
+            Instruction::ScrollDown { n } => {
+                self.scroll(0, n as isize);
+            }
+
+            Instruction::Cls => {
+                // Only the selected planes are cleared
+                for pixel in self.screen.iter_mut() {
+                    *pixel &= !self.planes;
+                }
+            }
+
+            Instruction::Ret => {
+                self.pc = self.pop()?;
+            }
+
+            Instruction::ScrollRight => {
+                self.scroll(4, 0);
+            }
+
+            Instruction::ScrollLeft => {
+                self.scroll(-4, 0);
+            }
+
+            Instruction::Exit => {
+                self.halted = true;
+                let addr = self.current.0;
+                self.log(format_args!("Program exited with 00FD at {:03X}", addr));
+            }
+
+            Instruction::LowRes => {
+                self.hires = false;
+                self.screen.fill(0);
+            }
+
+            Instruction::HighRes => {
+                self.hires = true;
+                self.screen.fill(0);
+            }
+
+            Instruction::Jump { nnn } => {
+                self.pc = nnn;
+            }
+
+            Instruction::Call { nnn } => {
+                self.push(self.pc)?;
+                self.pc = nnn;
+            }
+
+            // SKIP next if VX == NN
+            Instruction::SkipEqImm { x, nn } => {
+                if self.v[x] == nn {
+                    self.skip()?;
+                }
+            }
+
+            Instruction::SkipNeImm { x, nn } => {
+                if self.v[x] != nn {
+                    self.skip()?;
+                }
+            }
+
+            Instruction::SkipEqReg { x, y } => {
+                if self.v[x] == self.v[y] {
+                    self.skip()?;
+                }
+            }
+
+            Instruction::SaveRange { x, y } => {
+                // Registers are stored in the order given, so x may be above y. I is left alone
+                for offset in 0..=x.abs_diff(y) {
+                    let reg = Self::register_from(x, y, offset);
+                    self.write_data(self.index as usize + offset, self.v[reg])?;
+                }
+            }
+
+            Instruction::LoadRange { x, y } => {
+                for offset in 0..=x.abs_diff(y) {
+                    let reg = Self::register_from(x, y, offset);
+                    self.v[reg] = self.read_data(self.index as usize + offset)?;
+                }
+            }
+
+            Instruction::LoadImm { x, nn } => {
+                self.v[x] = nn;
+            }
+
+            Instruction::AddImm { x, nn } => {
+                self.v[x] = self.v[x].wrapping_add(nn);
+            }
+
+            Instruction::LoadReg { x, y } => {
+                self.v[x] = self.v[y];
+            }
+
+            Instruction::Or { x, y } => {
+                self.v[x] |= self.v[y];
+                if self.quirks.logic_resets_vf { self.v[0xF] = 0; }
+            }
+
+            Instruction::And { x, y } => {
+                self.v[x] &= self.v[y];
+                if self.quirks.logic_resets_vf { self.v[0xF] = 0; }
+            }
+
+            Instruction::Xor { x, y } => {
+                self.v[x] ^= self.v[y];
+                if self.quirks.logic_resets_vf { self.v[0xF] = 0; }
+            }
+
+            Instruction::AddReg { x, y } => {
+                let (sum, overflow) = self.v[x].overflowing_add(self.v[y]);
+
+                self.v[x] = sum;
+                self.v[0xF] = overflow as u8;
+            }
+
+            Instruction::SubReg { x, y } => {
+                let (sum, underflow) = self.v[x].overflowing_sub(self.v[y]);
+
+                let underflow = if underflow { 0 } else { 1 };
+
+                self.v[x] = sum;
+                self.v[0xF] = underflow;
+            }
+
+            Instruction::ShiftRight { x, y } => {
+                if self.quirks.shift_uses_vy { self.v[x] = self.v[y]; }
+
+                let dropoff = self.v[x] & 1;
+
+                self.v[x] >>= 1;
+                self.v[0xF] = dropoff;
+            }
+
+            Instruction::SubNReg { x, y } => {
+                let (sum, underflow) = self.v[y].overflowing_sub(self.v[x]);
+
+                let underflow = if underflow { 0 } else { 1 };
+
+                self.v[x] = sum;
+                self.v[0xF] = underflow;
+            }
+
+            Instruction::ShiftLeft { x, y } => {
+                if self.quirks.shift_uses_vy { self.v[x] = self.v[y]; }
+
+                let dropoff = (self.v[x] >> 7) & 1;
+
+                self.v[x] <<= 1;
+                self.v[0xF] = dropoff;
+            }
+
+            Instruction::SkipNeReg { x, y } => {
+                if self.v[x] != self.v[y] {
+                    self.skip()?;
+                }
+            }
+
+            Instruction::LoadIndex { nnn } => {
+                self.index = nnn;
+            }
+
+            Instruction::JumpV0 { nnn } => {
+                // With the quirk enabled the high nibble of nnn doubles as the register
+                let offset = if self.quirks.jump_uses_vx { self.v[(nnn >> 8) as usize] } else { self.v[0] };
+                self.pc = nnn + offset as u16;
+            }
+
+            Instruction::Random { x, nn } => {
+                self.v[x] = self.random.random_byte() & nn;
+            }
+
+            Instruction::Draw { x, y, n } => {
+                let (width, height) = (self.screen_width(), self.screen_height());
+
+                // Draw coordinates are stored in register X and Y. The starting
+                // position always wraps, even when the sprite itself is clipped
+                let x_cord = self.v[x] as usize % width;
+                let y_cord = self.v[y] as usize % height;
+
+                // Dxy0 draws a 16x16 sprite stored as two bytes per row
+                let (sprite_width, rows) = if n == 0 { (16, 16) } else { (8, n as usize) };
+                let bytes_per_row = sprite_width / 8;
+
+                // Keep track of whether or not we overwrote a pixel when we drew
+                let mut erased = false;
+
+                // With several planes selected, the sprite for each plane follows the previous one
+                let mut addr = self.index as usize;
+
+                let selected = self.planes;
+                for plane in (0..NUM_PLANES).map(|p| 1u8 << p).filter(|p| selected & p != 0) {
+                    for row in 0..rows {
+                        // Find where our sprite data is stored
+                        let mut pixels = 0u16;
+                        for _ in 0..bytes_per_row {
+                            pixels = (pixels << 8) | self.read_data(addr)? as u16;
+                            addr += 1;
+                        }
+
+                        for column in 0..sprite_width {
+                            // Use a mask to fetch current pixel's bit. Only flip if a 1
+                            if (pixels & (1 << (sprite_width - 1 - column))) != 0 {
+                                let x = x_cord + column;
+                                let y = y_cord + row;
+
+                                if self.quirks.clip_sprites && (x >= width || y >= height) {
+                                    continue;
+                                }
+
+                                // Otherwise sprites wrap around screen, so use modulo
+                                let x = x % width;
+                                let y = y % height;
+
+                                // Get pixel index for our screen array
+                                let idx = x + width * y;
+
+                                // Check if we are going to erase a pixel
+                                erased |= self.screen[idx] & plane != 0;
+
+                                self.screen[idx] ^= plane;
+                            }
+                        }
+                    }
+                }
+
+                // Populate Vf
+                self.v[0xF] = erased as u8;
+            }
+
+            Instruction::SkipKeyPressed { x } => {
+                let vx = self.v[x];
+                let key = self.keys[(vx & 0xF) as usize];
+
+                if key {
+                    self.skip()?;
+                }
+            }
+
+            Instruction::SkipKeyNotPressed { x } => {
+                let vx = self.v[x];
+                let key = self.keys[(vx & 0xF) as usize];
+
+                if !key {
+                    self.skip()?;
+                }
+            }
+
+            Instruction::LoadIndexLong => {
+                // The 16 bit address is stored in the word after the opcode
+                self.index = self.fetch()?;
+            }
+
+            Instruction::SelectPlane { n } => {
+                self.planes = n & ((1 << NUM_PLANES) - 1);
+            }
+
+            Instruction::LoadAudio => {
+                for idx in 0..AUDIO_PATTERN_SIZE {
+                    self.audio_pattern[idx] = self.read_data(self.index as usize + idx)?;
+                }
+            }
+
+            Instruction::LoadDelay { x } => {
+                self.v[x] = self.dt;
+            }
+
+            Instruction::WaitKey { x } => {
+                // Keys is an array of bools, so we are looking for the first one that is true
+                match self.keys.iter().position(|&pressed| pressed) {
+                    Some(i) => self.v[x] = i as u8,
+                    None => self.pc = self.pc.wrapping_sub(2),
+                }
+            }
+
+            Instruction::SetDelay { x } => {
+                self.dt = self.v[x];
+            }
+
+            Instruction::SetSound { x } => {
+                self.st = self.v[x];
+            }
+
+            Instruction::AddIndex { x } => {
+                self.index = self.index.wrapping_add(self.v[x] as u16);
+            }
+
+            Instruction::LoadFont { x } => {
+                // Each font character is 5 bytes long and starts at address 0
+                self.index = (self.v[x] as u16 & 0xF) * 5;
+            }
+
+            Instruction::LoadBigFont { x } => {
+                // Big characters are 10 bytes long and follow the small font
+                self.index = (BIG_FONTSET_ADDR + (self.v[x] as usize & 0xF) * 10) as u16;
+            }
+
+            Instruction::StoreBcd { x } => {
+                let vx = self.v[x];
+
+                let hundreds = vx / 100;
+                let tens = (vx / 10) % 10;
+                let ones = vx % 10;
+
+                self.write_data(self.index as usize, hundreds)?;
+                self.write_data(self.index as usize + 1, tens)?;
+                self.write_data(self.index as usize + 2, ones)?;
+            }
+
+            Instruction::SetPitch { x } => {
+                self.pitch = self.v[x];
+            }
+
+            Instruction::StoreRegs { x } => {
+                for idx in 0..=x {
+                    self.write_data(self.index as usize + idx, self.v[idx])?;
+                }
+
+                self.increment_index(x);
+            }
+
+            Instruction::LoadRegs { x } => {
+                for idx in 0..=x {
+                    self.v[idx] = self.read_data(self.index as usize + idx)?;
+                }
+
+                self.increment_index(x);
+            }
+
+            Instruction::StoreFlags { x } => {
+                self.rpl[..=x].copy_from_slice(&self.v[..=x]);
+            }
+
+            Instruction::LoadFlags { x } => {
+                self.v[..=x].copy_from_slice(&self.rpl[..=x]);
+            }
+        }
+
+        Ok(())
+    }
+
+    // One 60 Hz frame the way the SDL frontend runs it at the default
+    // speed: TICKS_PER_FRAME instructions, or with VIP timing as many as
+    // fit in the frame, then a timer tick
+    pub fn run_frame(&mut self) -> Result<(), EmulatorError> {
+        match self.timing {
+            Timing::Fixed => {
+                for _ in 0..TICKS_PER_FRAME {
+                    self.cycle()?;
+                }
+            }
+            Timing::Vip => {
+                while !self.frame_finished() {
+                    self.cycle()?;
+                }
+            }
+        }
+        self.increment_timers();
+
+        Ok(())
+    }
+
+    // Whether the VIP would be waiting for the display interrupt by now.
+    // Always false with fixed timing, where the frontend counts instructions
+    pub fn frame_finished(&self) -> bool {
+        self.timing == Timing::Vip && (self.frame_cycles <= 0 || self.display_wait || self.halted)
+    }
+
+    pub fn increment_timers(&mut self) {
+        if self.dt > 0 { self.dt -= 1; }
+
+        if self.st > 0 { self.st -= 1; }
+
+        self.random.tick();
+
+        // The interrupt starts the next frame, minus any cycles the last instruction overran by
+        self.frame_cycles = self.frame_cycles.min(0) + CPU_CYCLES_PER_FRAME as i32;
+        self.display_ready = self.display_wait;
+        self.display_wait = false;
+    }
+
+    pub fn push(&mut self, value: u16) -> Result<(), EmulatorError> {
+        if self.sp as usize >= STACK_SIZE { return Err(EmulatorError::StackOverflow); }
+
+        self.stack[self.sp as usize] = value;
+        self.sp += 1;
+
+        Ok(())
+    }
+
+    pub fn pop(&mut self) -> Result<u16, EmulatorError> {
+        if self.sp == 0 { return Err(EmulatorError::StackUnderflow); }
+
+        self.sp -= 1;
+        Ok(self.stack[self.sp as usize])
+    }
+
+    // Only the part of the buffer used by the current resolution is returned,
+    // laid out row by row with screen_width() pixels per row
+    // Each pixel is a bitmask of the planes lit at that position
+    pub fn get_screen(&self) -> &[u8] {
+        &self.screen[..self.screen_width() * self.screen_height()]
+    }
+
+    pub fn screen_width(&self) -> usize {
+        if self.hires { SCREEN_WIDTH } else { LORES_WIDTH }
+    }
+
+    pub fn screen_height(&self) -> usize {
+        if self.hires { SCREEN_HEIGHT } else { LORES_HEIGHT }
+    }
+
+    pub fn is_halted(&self) -> bool {
+        self.halted
+    }
+
+    pub fn set_timing(&mut self, timing: Timing) {
+        self.timing = timing;
+    }
+
+    pub fn timing(&self) -> Timing {
+        self.timing
+    }
+
+    pub fn memory_size(&self) -> usize {
+        self.ram.len()
+    }
+
+    // Read-only views of the machine for the debugger
+
+    pub fn pc(&self) -> u16 {
+        self.pc
+    }
+
+    pub fn sp(&self) -> u16 {
+        self.sp
+    }
+
+    pub fn index(&self) -> u16 {
+        self.index
+    }
+
+    pub fn registers(&self) -> &[u8; NUM_V_REG] {
+        &self.v
+    }
+
+    // Only the slots below the stack pointer, oldest return address first
+    pub fn stack(&self) -> &[u16] {
+        &self.stack[..self.sp as usize]
+    }
+
+    pub fn delay_timer(&self) -> u8 {
+        self.dt
+    }
+
+    pub fn sound_timer(&self) -> u8 {
+        self.st
+    }
+
+    // What the speaker should play right now. XO-CHIP programs that loaded
+    // an audio pattern get it played back, everything else gets the buzzer
+    pub fn tone(&self) -> Tone {
+        if self.st == 0 {
+            Tone::Silent
+        } else if self.quirks.xo_chip && self.audio_pattern != [0; AUDIO_PATTERN_SIZE] {
+            Tone::Pattern { pattern: self.audio_pattern, rate: pattern_rate(self.pitch) }
+        } else {
+            Tone::Beep
+        }
+    }
+
+    pub fn memory(&self) -> &[u8] {
+        &self.ram
+    }
+
+    pub fn key_press(&mut self, index: usize, pressed: bool) {
+        self.keys[index] = pressed;
+    }
+
+    // Snapshot the complete machine state. The quirks are configuration rather
+    // than state, so they are not included
+    pub fn save_state(&self) -> Vec<u8> {
+        let mut w = StateWriter::new();
+
+        w.u16(self.pc);
+        w.u16(self.sp);
+        w.u16(self.index);
+        w.u8(self.dt);
+        w.u8(self.st);
+        w.bytes(&self.v);
+        for addr in self.stack {
+            w.u16(addr);
+        }
+        for key in self.keys {
+            w.bool(key);
+        }
+        w.u32(self.memory_size() as u32);
+        w.bytes(&self.ram);
+        w.bytes(&self.screen);
+        w.bool(self.hires);
+        w.bool(self.halted);
+        w.bytes(&self.rpl);
+        w.u8(self.planes);
+        w.bytes(&self.audio_pattern);
+        w.u8(self.pitch);
+        w.u64(self.random.state());
+        w.u32(self.frame_cycles as u32);
+        w.bool(self.display_wait);
+        w.bool(self.display_ready);
+
+        w.finish()
+    }
+
+    // Restore a snapshot from save_state. Nothing is changed unless the whole state is valid
+    pub fn load_state(&mut self, data: &[u8]) -> Result<(), EmulatorError> {
+        let mut r = StateReader::new(data)?;
+
+        let pc = r.u16()?;
+        let sp = r.u16()?;
+        let index = r.u16()?;
+        let dt = r.u8()?;
+        let st = r.u8()?;
+        let mut v = [0; NUM_V_REG];
+        r.bytes(&mut v)?;
+        let mut stack = [0; STACK_SIZE];
+        for addr in stack.iter_mut() {
+            *addr = r.u16()?;
+        }
+        let mut keys = [false; NUM_KEYS];
+        for key in keys.iter_mut() {
+            *key = r.bool()?;
+        }
+        if r.u32()? as usize != self.memory_size() {
+            return Err(EmulatorError::InvalidState("memory size does not match the quirks profile"));
+        }
+        let ram = r.slice(self.ram.len())?;
+        let screen = r.slice(self.screen.len())?;
+        let hires = r.bool()?;
+        let halted = r.bool()?;
+        let mut rpl = [0; NUM_RPL_FLAGS];
+        r.bytes(&mut rpl)?;
+        let planes = r.u8()?;
+        let mut audio_pattern = [0; AUDIO_PATTERN_SIZE];
+        r.bytes(&mut audio_pattern)?;
+        let pitch = r.u8()?;
+        let random_state = r.u64()?;
+        let frame_cycles = r.u32()? as i32;
+        let display_wait = r.bool()?;
+        let display_ready = r.bool()?;
+        r.finish()?;
+
+        if sp as usize > STACK_SIZE {
+            return Err(EmulatorError::InvalidState("stack pointer out of range"));
+        }
+
+        self.pc = pc;
+        self.sp = sp;
+        self.index = index;
+        self.dt = dt;
+        self.st = st;
+        self.v = v;
+        self.stack = stack;
+        self.keys = keys;
+        self.ram.copy_from_slice(ram);
+        self.screen.copy_from_slice(screen);
+        self.hires = hires;
+        self.halted = halted;
+        self.rpl = rpl;
+        self.planes = planes & ((1 << NUM_PLANES) - 1);
+        self.audio_pattern = audio_pattern;
+        self.pitch = pitch;
+        self.random.set_state(random_state);
+        self.frame_cycles = frame_cycles;
+        self.display_wait = display_wait;
+        self.display_ready = display_ready;
+
+        Ok(())
+    }
+
+    pub fn load_rom(&mut self, rom: &[u8]) -> Result<(), EmulatorError> {
+        let max_program_memory = self.memory_size() - PROGRAM_START_ADDR;
+        if rom.len() > max_program_memory {
+            return Err(EmulatorError::RomTooLarge { size: rom.len(), max: max_program_memory });
+        }
+
+        // Start from a clean machine so a ROM can be reloaded at any time
+        self.reset();
+
+        let program_end_addr = PROGRAM_START_ADDR + rom.len();
+
+        self.ram[PROGRAM_START_ADDR..program_end_addr].copy_from_slice(rom);
+
+        Ok(())
+    }
+
+    pub fn add_watchpoint(&mut self, watchpoint: Watchpoint) {
+        if !self.watchpoints.contains(&watchpoint) {
+            self.watchpoints.push(watchpoint);
+        }
+    }
+
+    // Returns false if there was no such watchpoint
+    pub fn remove_watchpoint(&mut self, watchpoint: Watchpoint) -> bool {
+        let count = self.watchpoints.len();
+        self.watchpoints.retain(|w| *w != watchpoint);
+
+        self.watchpoints.len() != count
+    }
+
+    pub fn watchpoints(&self) -> &[Watchpoint] {
+        &self.watchpoints
+    }
+
+    // Hand over the watchpoint hits collected since the last call, oldest first
+    pub fn take_watch_hits(&mut self) -> Vec<WatchHit> {
+        core::mem::take(&mut self.watch_hits)
+    }
+
+    // Replace the source of Cxnn's random numbers, the default is a xorshift generator
+    pub fn set_random_source(&mut self, random: Box<dyn RandomSource>) {
+        self.random = random;
+    }
+
+    // Nothing is logged until a log is set
+    pub fn set_log(&mut self, log: Box<dyn Log>) {
+        self.log = Some(log);
+    }
+
+    fn watch_hit(&mut self, watchpoint: Watchpoint, old: u16, new: u16) {
+        let (addr, instruction) = self.current;
+        self.watch_hits.push(WatchHit { watchpoint, addr, instruction, old, new });
+    }
+
+    // Memory reads and writes made by instructions, as opposed to instruction
+    // fetches. These are the accesses memory watchpoints look at
+    fn read_data(&mut self, addr: usize) -> Result<u8, EmulatorError> {
+        let value = self.read(addr)?;
+
+        if self.watchpoints.contains(&Watchpoint::Read(addr as u16)) {
+            self.watch_hit(Watchpoint::Read(addr as u16), value as u16, value as u16);
+        }
+
+        Ok(value)
+    }
+
+    fn write_data(&mut self, addr: usize, value: u8) -> Result<(), EmulatorError> {
+        let old = self.read(addr)?;
+        self.write(addr, value)?;
+
+        if self.watchpoints.contains(&Watchpoint::Write(addr as u16)) {
+            self.watch_hit(Watchpoint::Write(addr as u16), old as u16, value as u16);
+        }
+
+        Ok(())
+    }
+
+    fn log(&mut self, message: fmt::Arguments) {
+        if let Some(log) = self.log.as_mut() {
+            log.log(message);
+        }
+    }
+
+    // Skip the next instruction. XO-CHIP's F000 nnnn is four bytes long, so it takes two words to skip
+    fn skip(&mut self) -> Result<(), EmulatorError> {
+        let next = (self.read(self.pc as usize)? as u16) << 8 | self.read(self.pc as usize + 1)? as u16;
+        let long = self.quirks.xo_chip && next == 0xF000;
+
+        self.pc = self.pc.wrapping_add(if long { 4 } else { 2 });
+        if self.timing == Timing::Vip {
+            self.frame_cycles -= SKIP_CYCLES as i32;
+        }
+
+        Ok(())
+    }
+
+    // Move the selected planes by (dx, dy) pixels, filling the gap with unlit pixels.
+    // Done in place, walking away from the direction of movement so every
+    // pixel is read before it is overwritten
+    fn scroll(&mut self, dx: isize, dy: isize) {
+        let (width, height) = (self.screen_width() as isize, self.screen_height() as isize);
+        let order = |i: isize, len: isize, delta: isize| if delta > 0 { len - 1 - i } else { i };
+
+        for row in 0..height {
+            let y = order(row, height, dy);
+            for column in 0..width {
+                let x = order(column, width, dx);
+                let (src_x, src_y) = (x - dx, y - dy);
+                let moved = if (0..width).contains(&src_x) && (0..height).contains(&src_y) {
+                    self.screen[(src_x + width * src_y) as usize] & self.planes
+                } else {
+                    0
+                };
+
+                let idx = (x + width * y) as usize;
+                self.screen[idx] = (self.screen[idx] & !self.planes) | moved;
+            }
+        }
+    }
+
+    // The register `offset` steps from x towards y, counting down if x > y
+    fn register_from(x: usize, y: usize, offset: usize) -> usize {
+        if x <= y { x + offset } else { x - offset }
+    }
+
+    fn increment_index(&mut self, x: usize) {
+        match self.quirks.index_increment {
+            IndexIncrement::None => (),
+            IndexIncrement::X => self.index = self.index.wrapping_add(x as u16),
+            IndexIncrement::XPlusOne => self.index = self.index.wrapping_add(x as u16 + 1),
+        }
+    }
+
+    fn read(&self, addr: usize) -> Result<u8, EmulatorError> {
+        self.ram.get(addr).copied().ok_or(EmulatorError::MemoryOutOfBounds { addr })
+    }
+
+    fn write(&mut self, addr: usize, value: u8) -> Result<(), EmulatorError> {
+        match self.ram.get_mut(addr) {
+            Some(byte) => { *byte = value; Ok(()) }
+            None => Err(EmulatorError::MemoryOutOfBounds { addr }),
+        }
+    }
+}
+
+// Without std there is nothing to seed from, so no_std hosts should set their own source
+#[cfg(feature = "std")]
+fn default_seed() -> u64 {
+    crate::host::random_seed()
+}
+
+#[cfg(not(feature = "std"))]
+fn default_seed() -> u64 {
+    0x2545_F491_4F6C_DD1D
+}
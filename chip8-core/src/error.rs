@@ -0,0 +1,38 @@
+use core::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EmulatorError {
+    // The opcode at `addr` does not decode to any known instruction
+    UnknownOpcode { addr: u16, opcode: u16 },
+    // CALL with all 16 stack slots in use
+    StackOverflow,
+    // RET with an empty stack
+    StackUnderflow,
+    RomTooLarge { size: usize, max: usize },
+    // An instruction tried to read or write outside of RAM
+    MemoryOutOfBounds { addr: usize },
+    // A save state could not be restored
+    InvalidState(&'static str),
+}
+
+impl fmt::Display for EmulatorError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EmulatorError::UnknownOpcode { addr, opcode } => {
+                write!(f, "unknown opcode {:04X} at address {:03X}", opcode, addr)
+            }
+            EmulatorError::StackOverflow => write!(f, "stack overflow, pointer at 16"),
+            EmulatorError::StackUnderflow => write!(f, "stack underflow, pointer at 0"),
+            EmulatorError::RomTooLarge { size, max } => {
+                write!(f, "ROM is {} bytes but only {} bytes of program memory are available", size, max)
+            }
+            EmulatorError::MemoryOutOfBounds { addr } => {
+                write!(f, "memory access out of bounds at address {:X}", addr)
+            }
+            EmulatorError::InvalidState(reason) => write!(f, "invalid save state: {}", reason),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for EmulatorError {}
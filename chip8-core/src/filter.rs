@@ -0,0 +1,139 @@
+use alloc::format;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::str::FromStr;
+
+use crate::emulator::Emulator;
+
+/*
+    CHIP-8 games move a sprite by XORing it off and drawing it again, and
+    anything drawn between two frames is only on screen for one of them, so
+    most games flicker. These filters run over each frame's screen to hide
+    that. Phosphor decay fades a pixel out over a few frames after it goes
+    dark, like an old CRT. Frame-OR shows a pixel lit if it was lit in this
+    frame or the one before.
+*/
+
+pub const DEFAULT_DECAY_FRAMES: u8 = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Filter {
+    None,
+    FrameOr,
+    // Frames a pixel takes to fade out
+    Decay(u8),
+}
+
+impl Filter {
+    pub const NAMES: [&'static str; 3] = ["none", "or", "decay"];
+}
+
+// decay takes an optional frame count, decay:8
+impl FromStr for Filter {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let text = text.to_ascii_lowercase();
+        let (name, frames) = match text.split_once(':') {
+            Some((name, frames)) => (name, Some(frames)),
+            None => (text.as_str(), None),
+        };
+
+        match (name, frames) {
+            ("none", None) => Ok(Filter::None),
+            ("or" | "frame-or", None) => Ok(Filter::FrameOr),
+            ("decay", None) => Ok(Filter::Decay(DEFAULT_DECAY_FRAMES)),
+            ("decay", Some(frames)) => match frames.parse() {
+                Ok(frames) if frames > 0 => Ok(Filter::Decay(frames)),
+                _ => Err(format!("invalid decay length '{}', expected 1-255 frames", frames)),
+            },
+            _ => Err(format!("unknown filter '{}', expected one of: {}", text, Self::NAMES.join(", "))),
+        }
+    }
+}
+
+// A pixel as it should be shown: the planes to take its colour from, and
+// how strongly, from 0 for the background to 255 for fully lit
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Pixel {
+    pub planes: u8,
+    pub level: u8,
+}
+
+pub struct ScreenFilter {
+    filter: Filter,
+    width: usize,
+    height: usize,
+    // The last planes each pixel had lit, and frames since then
+    planes: Vec<u8>,
+    age: Vec<u8>,
+    pixels: Vec<Pixel>,
+}
+
+impl ScreenFilter {
+    pub fn new(filter: Filter) -> Self {
+        Self {
+            filter,
+            width: 0,
+            height: 0,
+            planes: Vec::new(),
+            age: Vec::new(),
+            pixels: Vec::new(),
+        }
+    }
+
+    // Take in the emulator's screen. Call once per 60 Hz frame, the decay
+    // is counted in calls
+    pub fn push(&mut self, emu: &Emulator) {
+        let screen = emu.get_screen();
+
+        // History from another resolution is meaningless
+        if (emu.screen_width(), emu.screen_height()) != (self.width, self.height) {
+            self.width = emu.screen_width();
+            self.height = emu.screen_height();
+            self.planes = screen.to_vec();
+            self.age = vec![0; screen.len()];
+            self.pixels = vec![Pixel::default(); screen.len()];
+        }
+
+        for (i, &planes) in screen.iter().enumerate() {
+            self.pixels[i] = match self.filter {
+                Filter::None => Pixel { planes, level: lit(planes) },
+                Filter::FrameOr => {
+                    let shown = planes | self.planes[i];
+                    Pixel { planes: shown, level: lit(shown) }
+                }
+                Filter::Decay(frames) => {
+                    if planes != 0 {
+                        self.age[i] = 0;
+                    } else {
+                        self.age[i] = self.age[i].saturating_add(1).min(frames);
+                    }
+
+                    let shown = if planes != 0 { planes } else { self.planes[i] };
+                    let level = (frames - self.age[i]) as u32 * 255 / frames as u32;
+                    Pixel { planes: shown, level: if shown == 0 { 0 } else { level as u8 } }
+                }
+            };
+
+            if planes != 0 || self.filter == Filter::FrameOr {
+                self.planes[i] = planes;
+            }
+        }
+    }
+
+    // The filtered screen, laid out like Emulator::get_screen
+    pub fn pixels(&self) -> &[Pixel] {
+        &self.pixels
+    }
+
+    // Of the last screen pushed
+    pub fn width(&self) -> usize {
+        self.width
+    }
+}
+
+fn lit(planes: u8) -> u8 {
+    if planes != 0 { 255 } else { 0 }
+}
@@ -0,0 +1,164 @@
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::string::String;
+use core::fmt;
+use core::str::FromStr;
+
+/*
+    Services the interpreter needs from whatever it runs on. With the std
+    feature the emulator picks sensible defaults. On bare metal the host
+    plugs in its own with Emulator::set_random_source and
+    Emulator::set_log, for example a hardware RNG and a UART.
+*/
+
+// Supplies the bytes that Cxnn masks
+pub trait RandomSource {
+    fn random_byte(&mut self) -> u8;
+
+    // Save states keep the source's state so a restored game rolls the same
+    // numbers again. Sources with nothing worth saving can ignore these
+    fn state(&self) -> u64 {
+        0
+    }
+
+    fn set_state(&mut self, _state: u64) {}
+
+    // Called at 60 Hz along with the timers, for sources that depend on timing
+    fn tick(&mut self) {}
+}
+
+// Where the interpreter reports things worth knowing that are not errors
+pub trait Log {
+    fn log(&mut self, message: fmt::Arguments);
+}
+
+// xorshift64*, plenty random for games and cheap to snapshot. The default source
+pub struct Xorshift {
+    state: u64,
+}
+
+impl Xorshift {
+    pub fn new(seed: u64) -> Self {
+        // xorshift gets stuck on zero, so make sure the state never is
+        Self { state: seed | 1 }
+    }
+}
+
+impl RandomSource for Xorshift {
+    fn random_byte(&mut self) -> u8 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+
+        (self.state.wrapping_mul(0x2545_F491_4F6C_DD1D) >> 56) as u8
+    }
+
+    fn state(&self) -> u64 {
+        self.state
+    }
+
+    fn set_state(&mut self, state: u64) {
+        self.state = state | 1;
+    }
+}
+
+/*
+    The COSMAC VIP interpreter's Cxkk. The VIP keeps its seed in register
+    R9, which the 60 Hz display interrupt increments. Cxkk increments it
+    once more, reads the byte of the interpreter's own code on page 01 at
+    the seed's low byte, adds that into the seed's high byte and hands the
+    high byte back to be ANDed with kk. So the numbers depend on frame
+    timing and successive results are noticeably correlated, which some
+    games were tuned around.
+
+    The interpreter's code is not part of this emulator. Given a dump of its
+    page 01 the sequence is the VIP's own, otherwise a fixed scramble of the
+    low byte stands in for the code and only the pattern is the VIP's.
+*/
+pub struct VipRandom {
+    seed: u16,
+    // The interpreter's page 01, 0x0100-0x01FF
+    code: [u8; 256],
+}
+
+impl VipRandom {
+    pub fn new(seed: u64) -> Self {
+        let code = core::array::from_fn(|low| (low as u8).wrapping_mul(0x6D).rotate_left(3) ^ 0xA5);
+        Self::with_code(seed, code)
+    }
+
+    pub fn with_code(seed: u64, code: [u8; 256]) -> Self {
+        Self { seed: seed as u16, code }
+    }
+}
+
+impl RandomSource for VipRandom {
+    fn random_byte(&mut self) -> u8 {
+        self.seed = self.seed.wrapping_add(1);
+
+        let [high, low] = self.seed.to_be_bytes();
+        let high = high.wrapping_add(self.code[low as usize]);
+        self.seed = u16::from_be_bytes([high, low]);
+
+        high
+    }
+
+    fn state(&self) -> u64 {
+        self.seed as u64
+    }
+
+    fn set_state(&mut self, state: u64) {
+        self.seed = state as u16;
+    }
+
+    // The display interrupt
+    fn tick(&mut self) {
+        self.seed = self.seed.wrapping_add(1);
+    }
+}
+
+// The built-in random sources, for picking one by name
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RandomMode {
+    Xorshift,
+    Vip,
+}
+
+impl RandomMode {
+    pub const NAMES: [&'static str; 2] = ["xorshift", "vip"];
+
+    pub fn source(self, seed: u64) -> Box<dyn RandomSource> {
+        match self {
+            RandomMode::Xorshift => Box::new(Xorshift::new(seed)),
+            RandomMode::Vip => Box::new(VipRandom::new(seed)),
+        }
+    }
+}
+
+impl FromStr for RandomMode {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name.to_ascii_lowercase().as_str() {
+            "xorshift" => Ok(RandomMode::Xorshift),
+            "vip" | "cosmac" => Ok(RandomMode::Vip),
+            _ => Err(format!("unknown random mode '{}', expected one of: {}", name, Self::NAMES.join(", "))),
+        }
+    }
+}
+
+// A different seed every run, for when no --seed is given
+#[cfg(feature = "std")]
+pub fn random_seed() -> u64 {
+    rand::random()
+}
+
+#[cfg(feature = "std")]
+pub struct StderrLog;
+
+#[cfg(feature = "std")]
+impl Log for StderrLog {
+    fn log(&mut self, message: fmt::Arguments) {
+        eprintln!("{}", message);
+    }
+}
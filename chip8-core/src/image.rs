@@ -0,0 +1,104 @@
+/*
+    Just enough image encoding to save a screenshot without pulling in a
+    crate. PNGs are 8-bit greyscale with the pixel data in uncompressed
+    deflate blocks, which every decoder accepts. A CHIP-8 screen is at
+    most 8K pixels so the lack of compression does not matter.
+*/
+
+const PNG_SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1A, b'\n'];
+// Largest block a stored deflate block can hold
+const MAX_STORED_BLOCK: usize = 0xFFFF;
+
+// `pixels` holds one grey level per pixel, row by row
+pub fn encode_png(width: usize, height: usize, pixels: &[u8]) -> Vec<u8> {
+    let mut header = Vec::new();
+    header.extend((width as u32).to_be_bytes());
+    header.extend((height as u32).to_be_bytes());
+    // Bit depth 8, greyscale, deflate, adaptive filtering, no interlace
+    header.extend([8, 0, 0, 0, 0]);
+
+    // Every scanline starts with its filter type, 0 for none
+    let mut raw = Vec::with_capacity((width + 1) * height);
+    for row in pixels.chunks(width) {
+        raw.push(0);
+        raw.extend(row);
+    }
+
+    let mut png = PNG_SIGNATURE.to_vec();
+    write_chunk(&mut png, b"IHDR", &header);
+    write_chunk(&mut png, b"IDAT", &zlib_stored(&raw));
+    write_chunk(&mut png, b"IEND", &[]);
+
+    png
+}
+
+// Plain PBM, 1 for a lit pixel. Readable in a text editor, handy for diffs
+pub fn encode_pbm(width: usize, height: usize, pixels: &[bool]) -> Vec<u8> {
+    let mut pbm = format!("P1\n{} {}\n", width, height);
+
+    for row in pixels.chunks(width) {
+        let row: Vec<&str> = row.iter().map(|lit| if *lit { "1" } else { "0" }).collect();
+        pbm.push_str(&row.join(" "));
+        pbm.push('\n');
+    }
+
+    pbm.into_bytes()
+}
+
+fn write_chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    png.extend((data.len() as u32).to_be_bytes());
+
+    let start = png.len();
+    png.extend(kind);
+    png.extend(data);
+    // The CRC covers the chunk type and data but not the length
+    let crc = crc32(&png[start..]);
+    png.extend(crc.to_be_bytes());
+}
+
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    // Deflate, 32K window, no preset dictionary, fastest compression
+    let mut out = vec![0x78, 0x01];
+
+    let mut blocks = data.chunks(MAX_STORED_BLOCK).peekable();
+    if blocks.peek().is_none() {
+        // An empty stream still needs one final block
+        out.extend([1, 0, 0, 0xFF, 0xFF]);
+    }
+    while let Some(block) = blocks.next() {
+        let last = blocks.peek().is_none();
+        let len = block.len() as u16;
+
+        out.push(last as u8);
+        out.extend(len.to_le_bytes());
+        out.extend((!len).to_le_bytes());
+        out.extend(block);
+    }
+
+    out.extend(adler32(data).to_be_bytes());
+    out
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xEDB8_8320 } else { crc >> 1 };
+        }
+    }
+
+    !crc
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+
+    for byte in data {
+        a = (a + *byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+
+    (b << 16) | a
+}
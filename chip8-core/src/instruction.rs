@@ -0,0 +1,275 @@
+use core::fmt;
+
+/*
+    Every CHIP-8 opcode is two bytes long and is split into four nibbles.
+    The naming below follows the usual convention:
+
+        nnn - 12 bit address        x - register in the second nibble
+        nn  - 8 bit constant        y - register in the third nibble
+        n   - 4 bit constant
+*/
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Instruction {
+    Nop,                                    // 0000
+    ScrollDown { n: u8 },                   // 00Cn (SUPER-CHIP)
+    Cls,                                    // 00E0
+    Ret,                                    // 00EE
+    ScrollRight,                            // 00FB (SUPER-CHIP)
+    ScrollLeft,                             // 00FC (SUPER-CHIP)
+    Exit,                                   // 00FD (SUPER-CHIP)
+    LowRes,                                 // 00FE (SUPER-CHIP)
+    HighRes,                                // 00FF (SUPER-CHIP)
+    Jump { nnn: u16 },                      // 1nnn
+    Call { nnn: u16 },                      // 2nnn
+    SkipEqImm { x: usize, nn: u8 },         // 3xnn
+    SkipNeImm { x: usize, nn: u8 },         // 4xnn
+    SkipEqReg { x: usize, y: usize },       // 5xy0
+    SaveRange { x: usize, y: usize },       // 5xy2 (XO-CHIP)
+    LoadRange { x: usize, y: usize },       // 5xy3 (XO-CHIP)
+    LoadImm { x: usize, nn: u8 },           // 6xnn
+    AddImm { x: usize, nn: u8 },            // 7xnn
+    LoadReg { x: usize, y: usize },         // 8xy0
+    Or { x: usize, y: usize },              // 8xy1
+    And { x: usize, y: usize },             // 8xy2
+    Xor { x: usize, y: usize },             // 8xy3
+    AddReg { x: usize, y: usize },          // 8xy4
+    SubReg { x: usize, y: usize },          // 8xy5
+    ShiftRight { x: usize, y: usize },      // 8xy6
+    SubNReg { x: usize, y: usize },         // 8xy7
+    ShiftLeft { x: usize, y: usize },       // 8xyE
+    SkipNeReg { x: usize, y: usize },       // 9xy0
+    LoadIndex { nnn: u16 },                 // Annn
+    JumpV0 { nnn: u16 },                    // Bnnn
+    Random { x: usize, nn: u8 },            // Cxnn
+    Draw { x: usize, y: usize, n: u8 },     // Dxyn, Dxy0 draws a 16x16 sprite
+    SkipKeyPressed { x: usize },            // Ex9E
+    SkipKeyNotPressed { x: usize },         // ExA1
+    LoadIndexLong,                          // F000 nnnn (XO-CHIP), nnnn is the following word
+    SelectPlane { n: u8 },                  // Fn01 (XO-CHIP)
+    LoadAudio,                              // F002 (XO-CHIP)
+    LoadDelay { x: usize },                 // Fx07
+    WaitKey { x: usize },                   // Fx0A
+    SetDelay { x: usize },                  // Fx15
+    SetSound { x: usize },                  // Fx18
+    AddIndex { x: usize },                  // Fx1E
+    LoadFont { x: usize },                  // Fx29
+    LoadBigFont { x: usize },               // Fx30 (SUPER-CHIP)
+    StoreBcd { x: usize },                  // Fx33
+    SetPitch { x: usize },                  // Fx3A (XO-CHIP)
+    StoreRegs { x: usize },                 // Fx55
+    LoadRegs { x: usize },                  // Fx65
+    StoreFlags { x: usize },                // Fx75 (SUPER-CHIP)
+    LoadFlags { x: usize },                 // Fx85 (SUPER-CHIP)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecodeError {
+    UnknownOpcode(u16),
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DecodeError::UnknownOpcode(op) => write!(f, "unknown opcode {:04X}", op),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for DecodeError {}
+
+// Turn a raw opcode into an Instruction. This touches no emulator state,
+// so tools such as a disassembler can share it with the interpreter.
+pub fn decode(op: u16) -> Result<Instruction, DecodeError> {
+    let digit1 = (op & 0xF000) >> 12;
+    let digit2 = (op & 0x0F00) >> 8;
+    let digit3 = (op & 0x00F0) >> 4;
+    let digit4 = op & 0x000F;
+
+    let x = digit2 as usize;
+    let y = digit3 as usize;
+    let n = digit4 as u8;
+    let nn = (op & 0xFF) as u8;
+    let nnn = op & 0xFFF;
+
+    let instruction = match (digit1, digit2, digit3, digit4) {
+        (0, 0, 0, 0) => Instruction::Nop,
+        (0, 0, 0xC, _) => Instruction::ScrollDown { n },
+        (0, 0, 0xE, 0) => Instruction::Cls,
+        (0, 0, 0xE, 0xE) => Instruction::Ret,
+        (0, 0, 0xF, 0xB) => Instruction::ScrollRight,
+        (0, 0, 0xF, 0xC) => Instruction::ScrollLeft,
+        (0, 0, 0xF, 0xD) => Instruction::Exit,
+        (0, 0, 0xF, 0xE) => Instruction::LowRes,
+        (0, 0, 0xF, 0xF) => Instruction::HighRes,
+        (1, _, _, _) => Instruction::Jump { nnn },
+        (2, _, _, _) => Instruction::Call { nnn },
+        (3, _, _, _) => Instruction::SkipEqImm { x, nn },
+        (4, _, _, _) => Instruction::SkipNeImm { x, nn },
+        (5, _, _, 0) => Instruction::SkipEqReg { x, y },
+        (5, _, _, 2) => Instruction::SaveRange { x, y },
+        (5, _, _, 3) => Instruction::LoadRange { x, y },
+        (6, _, _, _) => Instruction::LoadImm { x, nn },
+        (7, _, _, _) => Instruction::AddImm { x, nn },
+        (8, _, _, 0) => Instruction::LoadReg { x, y },
+        (8, _, _, 1) => Instruction::Or { x, y },
+        (8, _, _, 2) => Instruction::And { x, y },
+        (8, _, _, 3) => Instruction::Xor { x, y },
+        (8, _, _, 4) => Instruction::AddReg { x, y },
+        (8, _, _, 5) => Instruction::SubReg { x, y },
+        (8, _, _, 6) => Instruction::ShiftRight { x, y },
+        (8, _, _, 7) => Instruction::SubNReg { x, y },
+        (8, _, _, 0xE) => Instruction::ShiftLeft { x, y },
+        (9, _, _, 0) => Instruction::SkipNeReg { x, y },
+        (0xA, _, _, _) => Instruction::LoadIndex { nnn },
+        (0xB, _, _, _) => Instruction::JumpV0 { nnn },
+        (0xC, _, _, _) => Instruction::Random { x, nn },
+        (0xD, _, _, _) => Instruction::Draw { x, y, n },
+        (0xE, _, 9, 0xE) => Instruction::SkipKeyPressed { x },
+        (0xE, _, 0xA, 1) => Instruction::SkipKeyNotPressed { x },
+        (0xF, 0, 0, 0) => Instruction::LoadIndexLong,
+        (0xF, _, 0, 1) => Instruction::SelectPlane { n: digit2 as u8 },
+        (0xF, 0, 0, 2) => Instruction::LoadAudio,
+        (0xF, _, 0, 7) => Instruction::LoadDelay { x },
+        (0xF, _, 0, 0xA) => Instruction::WaitKey { x },
+        (0xF, _, 1, 5) => Instruction::SetDelay { x },
+        (0xF, _, 1, 8) => Instruction::SetSound { x },
+        (0xF, _, 1, 0xE) => Instruction::AddIndex { x },
+        (0xF, _, 2, 9) => Instruction::LoadFont { x },
+        (0xF, _, 3, 0) => Instruction::LoadBigFont { x },
+        (0xF, _, 3, 3) => Instruction::StoreBcd { x },
+        (0xF, _, 3, 0xA) => Instruction::SetPitch { x },
+        (0xF, _, 5, 5) => Instruction::StoreRegs { x },
+        (0xF, _, 6, 5) => Instruction::LoadRegs { x },
+        (0xF, _, 7, 5) => Instruction::StoreFlags { x },
+        (0xF, _, 8, 5) => Instruction::LoadFlags { x },
+
+        (_, _, _, _) => return Err(DecodeError::UnknownOpcode(op)),
+    };
+
+    Ok(instruction)
+}
+
+impl Instruction {
+    // The inverse of decode(). For F000 this is only the first word, the
+    // address that follows it has to be emitted separately
+    pub fn encode(&self) -> u16 {
+        let xy = |op: u16, x: usize, y: usize, n: u16| op | ((x as u16) << 8) | ((y as u16) << 4) | n;
+        let xnn = |op: u16, x: usize, nn: u8| op | ((x as u16) << 8) | nn as u16;
+        let fx = |x: usize, nn: u16| 0xF000 | ((x as u16) << 8) | nn;
+
+        match *self {
+            Instruction::Nop => 0x0000,
+            Instruction::ScrollDown { n } => 0x00C0 | n as u16,
+            Instruction::Cls => 0x00E0,
+            Instruction::Ret => 0x00EE,
+            Instruction::ScrollRight => 0x00FB,
+            Instruction::ScrollLeft => 0x00FC,
+            Instruction::Exit => 0x00FD,
+            Instruction::LowRes => 0x00FE,
+            Instruction::HighRes => 0x00FF,
+            Instruction::Jump { nnn } => 0x1000 | nnn,
+            Instruction::Call { nnn } => 0x2000 | nnn,
+            Instruction::SkipEqImm { x, nn } => xnn(0x3000, x, nn),
+            Instruction::SkipNeImm { x, nn } => xnn(0x4000, x, nn),
+            Instruction::SkipEqReg { x, y } => xy(0x5000, x, y, 0),
+            Instruction::SaveRange { x, y } => xy(0x5000, x, y, 2),
+            Instruction::LoadRange { x, y } => xy(0x5000, x, y, 3),
+            Instruction::LoadImm { x, nn } => xnn(0x6000, x, nn),
+            Instruction::AddImm { x, nn } => xnn(0x7000, x, nn),
+            Instruction::LoadReg { x, y } => xy(0x8000, x, y, 0),
+            Instruction::Or { x, y } => xy(0x8000, x, y, 1),
+            Instruction::And { x, y } => xy(0x8000, x, y, 2),
+            Instruction::Xor { x, y } => xy(0x8000, x, y, 3),
+            Instruction::AddReg { x, y } => xy(0x8000, x, y, 4),
+            Instruction::SubReg { x, y } => xy(0x8000, x, y, 5),
+            Instruction::ShiftRight { x, y } => xy(0x8000, x, y, 6),
+            Instruction::SubNReg { x, y } => xy(0x8000, x, y, 7),
+            Instruction::ShiftLeft { x, y } => xy(0x8000, x, y, 0xE),
+            Instruction::SkipNeReg { x, y } => xy(0x9000, x, y, 0),
+            Instruction::LoadIndex { nnn } => 0xA000 | nnn,
+            Instruction::JumpV0 { nnn } => 0xB000 | nnn,
+            Instruction::Random { x, nn } => xnn(0xC000, x, nn),
+            Instruction::Draw { x, y, n } => xy(0xD000, x, y, n as u16),
+            Instruction::SkipKeyPressed { x } => xnn(0xE000, x, 0x9E),
+            Instruction::SkipKeyNotPressed { x } => xnn(0xE000, x, 0xA1),
+            Instruction::LoadIndexLong => 0xF000,
+            Instruction::SelectPlane { n } => fx(n as usize, 0x01),
+            Instruction::LoadAudio => 0xF002,
+            Instruction::LoadDelay { x } => fx(x, 0x07),
+            Instruction::WaitKey { x } => fx(x, 0x0A),
+            Instruction::SetDelay { x } => fx(x, 0x15),
+            Instruction::SetSound { x } => fx(x, 0x18),
+            Instruction::AddIndex { x } => fx(x, 0x1E),
+            Instruction::LoadFont { x } => fx(x, 0x29),
+            Instruction::LoadBigFont { x } => fx(x, 0x30),
+            Instruction::StoreBcd { x } => fx(x, 0x33),
+            Instruction::SetPitch { x } => fx(x, 0x3A),
+            Instruction::StoreRegs { x } => fx(x, 0x55),
+            Instruction::LoadRegs { x } => fx(x, 0x65),
+            Instruction::StoreFlags { x } => fx(x, 0x75),
+            Instruction::LoadFlags { x } => fx(x, 0x85),
+        }
+    }
+}
+
+// Mnemonics follow Cowgod's CHIP-8 reference, with the usual names for the
+// SUPER-CHIP and XO-CHIP additions
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Instruction::Nop => write!(f, "NOP"),
+            Instruction::ScrollDown { n } => write!(f, "SCD {}", n),
+            Instruction::Cls => write!(f, "CLS"),
+            Instruction::Ret => write!(f, "RET"),
+            Instruction::ScrollRight => write!(f, "SCR"),
+            Instruction::ScrollLeft => write!(f, "SCL"),
+            Instruction::Exit => write!(f, "EXIT"),
+            Instruction::LowRes => write!(f, "LOW"),
+            Instruction::HighRes => write!(f, "HIGH"),
+            Instruction::Jump { nnn } => write!(f, "JP 0x{:03X}", nnn),
+            Instruction::Call { nnn } => write!(f, "CALL 0x{:03X}", nnn),
+            Instruction::SkipEqImm { x, nn } => write!(f, "SE V{:X}, 0x{:02X}", x, nn),
+            Instruction::SkipNeImm { x, nn } => write!(f, "SNE V{:X}, 0x{:02X}", x, nn),
+            Instruction::SkipEqReg { x, y } => write!(f, "SE V{:X}, V{:X}", x, y),
+            Instruction::SaveRange { x, y } => write!(f, "SAVE V{:X}, V{:X}", x, y),
+            Instruction::LoadRange { x, y } => write!(f, "LOAD V{:X}, V{:X}", x, y),
+            Instruction::LoadImm { x, nn } => write!(f, "LD V{:X}, 0x{:02X}", x, nn),
+            Instruction::AddImm { x, nn } => write!(f, "ADD V{:X}, 0x{:02X}", x, nn),
+            Instruction::LoadReg { x, y } => write!(f, "LD V{:X}, V{:X}", x, y),
+            Instruction::Or { x, y } => write!(f, "OR V{:X}, V{:X}", x, y),
+            Instruction::And { x, y } => write!(f, "AND V{:X}, V{:X}", x, y),
+            Instruction::Xor { x, y } => write!(f, "XOR V{:X}, V{:X}", x, y),
+            Instruction::AddReg { x, y } => write!(f, "ADD V{:X}, V{:X}", x, y),
+            Instruction::SubReg { x, y } => write!(f, "SUB V{:X}, V{:X}", x, y),
+            Instruction::ShiftRight { x, y } => write!(f, "SHR V{:X}, V{:X}", x, y),
+            Instruction::SubNReg { x, y } => write!(f, "SUBN V{:X}, V{:X}", x, y),
+            Instruction::ShiftLeft { x, y } => write!(f, "SHL V{:X}, V{:X}", x, y),
+            Instruction::SkipNeReg { x, y } => write!(f, "SNE V{:X}, V{:X}", x, y),
+            Instruction::LoadIndex { nnn } => write!(f, "LD I, 0x{:03X}", nnn),
+            Instruction::JumpV0 { nnn } => write!(f, "JP V0, 0x{:03X}", nnn),
+            Instruction::Random { x, nn } => write!(f, "RND V{:X}, 0x{:02X}", x, nn),
+            Instruction::Draw { x, y, n } => write!(f, "DRW V{:X}, V{:X}, {}", x, y, n),
+            Instruction::SkipKeyPressed { x } => write!(f, "SKP V{:X}", x),
+            Instruction::SkipKeyNotPressed { x } => write!(f, "SKNP V{:X}", x),
+            // The address is in the following word, which the decoder never sees
+            Instruction::LoadIndexLong => write!(f, "LD I, LONG"),
+            Instruction::SelectPlane { n } => write!(f, "PLANE {}", n),
+            Instruction::LoadAudio => write!(f, "AUDIO"),
+            Instruction::LoadDelay { x } => write!(f, "LD V{:X}, DT", x),
+            Instruction::WaitKey { x } => write!(f, "LD V{:X}, K", x),
+            Instruction::SetDelay { x } => write!(f, "LD DT, V{:X}", x),
+            Instruction::SetSound { x } => write!(f, "LD ST, V{:X}", x),
+            Instruction::AddIndex { x } => write!(f, "ADD I, V{:X}", x),
+            Instruction::LoadFont { x } => write!(f, "LD F, V{:X}", x),
+            Instruction::LoadBigFont { x } => write!(f, "LD HF, V{:X}", x),
+            Instruction::StoreBcd { x } => write!(f, "LD B, V{:X}", x),
+            Instruction::SetPitch { x } => write!(f, "PITCH V{:X}", x),
+            Instruction::StoreRegs { x } => write!(f, "LD [I], V{:X}", x),
+            Instruction::LoadRegs { x } => write!(f, "LD V{:X}, [I]", x),
+            Instruction::StoreFlags { x } => write!(f, "LD R, V{:X}", x),
+            Instruction::LoadFlags { x } => write!(f, "LD V{:X}, R", x),
+        }
+    }
+}
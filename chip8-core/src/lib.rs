@@ -0,0 +1,31 @@
+// The interpreter and the tools built on it, with no SDL dependency. The SDL frontend is the chip8 crate at the workspace root
+//
+// Without the default std feature only the interpreter is built, for
+// microcontrollers and other hosts with an allocator but no operating
+// system. See host.rs for what such a host has to provide.
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+#[cfg(feature = "std")]
+pub mod asm;
+#[cfg(feature = "std")]
+pub mod disasm;
+pub mod emulator;
+pub mod error;
+pub mod filter;
+pub mod host;
+#[cfg(feature = "std")]
+pub mod image;
+pub mod instruction;
+#[cfg(feature = "std")]
+pub mod octo;
+pub mod quirks;
+pub mod sound;
+mod state;
+#[cfg(feature = "std")]
+pub mod symbols;
+pub mod timing;
+pub mod watch;
+#[cfg(feature = "std")]
+pub mod wav;
@@ -0,0 +1,861 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use crate::asm::{AsmError, Assembly};
+use crate::emulator::PROGRAM_START_ADDR;
+use crate::instruction::Instruction;
+use crate::symbols::Symbols;
+
+/*
+    Compiler for Octo, the high level CHIP-8 assembly language used by
+    most community ROMs. Source is a stream of whitespace separated tokens,
+    with `#` starting a comment:
+
+        : main                      # a label, execution starts at main
+            v0 := 5                 # vx := / += / -= / =- / |= / &= / ^= / >>= / <<=
+            i := sprite-data        # i := long label for XO-CHIP's F000
+            loop
+                sprite v0 v1 4
+                v0 += 1
+                while v0 != 20      # leave the loop when false
+            again
+            if v0 == v1 then v2 := 1
+            if v0 key begin ... else ... end
+            draw-thing              # a bare label name calls it
+        :alias x v3
+        :const SPEED 4
+        :calc HALF { SPEED / 2 }    # right to left, no precedence
+        :macro twice OP { OP OP }
+
+    Conditions compare a register with a register or a byte using ==, !=,
+    <, >, <=, >=, or test a key with `key` and `-key`. The four ordered
+    comparisons clobber vf. The statement after `then` must be a single
+    two byte instruction. Bare numbers are emitted as data bytes.
+
+    Like Octo, the first instruction is a jump to main. Labels may be used
+    before they are defined, the addresses are patched in at the end.
+*/
+
+const MEMORY_SIZE: usize = 0x10000;
+// Macro expansions allowed in one program before we assume a macro calls itself forever
+const MAX_EXPANSIONS: usize = 100_000;
+
+#[derive(Clone)]
+struct Token {
+    text: String,
+    line: usize,
+}
+
+struct Macro {
+    args: Vec<String>,
+    body: Vec<Token>,
+}
+
+#[derive(Clone, Copy)]
+enum FixupKind {
+    // Low 12 bits of the instruction at the fixup address
+    Address,
+    // The word after F000
+    Long,
+}
+
+struct Fixup {
+    addr: usize,
+    kind: FixupKind,
+    label: String,
+    line: usize,
+}
+
+enum Block {
+    // `if ... begin`, the jump over the block taken when the condition is false
+    If { jump: usize, has_else: bool },
+    // `loop`, with the jumps out of it made by `while`
+    Loop { start: usize, breaks: Vec<usize> },
+}
+
+#[derive(Clone, Copy)]
+enum Operand {
+    Register(usize),
+    Byte(u8),
+}
+
+struct Compiler {
+    file: String,
+    // Tokens still to compile, in reverse so the next one is at the end
+    tokens: Vec<Token>,
+    // Line of the token being compiled, for errors
+    line: usize,
+    rom: Vec<u8>,
+    addr: usize,
+    labels: HashMap<String, u16>,
+    constants: HashMap<String, f64>,
+    aliases: HashMap<String, usize>,
+    macros: HashMap<String, Macro>,
+    fixups: Vec<Fixup>,
+    blocks: Vec<Block>,
+    expansions: usize,
+}
+
+// Compile an Octo source file
+pub fn compile_file(path: &Path) -> Result<Assembly, AsmError> {
+    let name = path.display().to_string();
+    let source = fs::read_to_string(path).map_err(|e| AsmError {
+        file: name.clone(),
+        line: 0,
+        message: format!("unable to read {}: {}", name, e),
+    })?;
+
+    Compiler::new(&name, &source).compile()
+}
+
+// Compile Octo source held in memory
+pub fn compile(source: &str) -> Result<Assembly, AsmError> {
+    Compiler::new("<input>", source).compile()
+}
+
+impl Compiler {
+    fn new(file: &str, source: &str) -> Self {
+        let mut tokens = Vec::new();
+        for (i, line) in source.lines().enumerate() {
+            let code = line.split('#').next().unwrap();
+            tokens.extend(code.split_whitespace().map(|text| Token { text: String::from(text), line: i + 1 }));
+        }
+        tokens.reverse();
+
+        Self {
+            file: String::from(file),
+            tokens,
+            line: 0,
+            rom: Vec::new(),
+            addr: PROGRAM_START_ADDR,
+            labels: HashMap::new(),
+            constants: HashMap::new(),
+            aliases: HashMap::new(),
+            macros: HashMap::new(),
+            fixups: Vec::new(),
+            blocks: Vec::new(),
+            expansions: 0,
+        }
+    }
+
+    fn compile(mut self) -> Result<Assembly, AsmError> {
+        // Reserve the jump to main
+        self.emit(Instruction::Jump { nnn: 0 })?;
+
+        while let Some(token) = self.tokens.pop() {
+            self.line = token.line;
+            self.statement(&token.text)?;
+        }
+
+        if let Some(block) = self.blocks.last() {
+            let missing = if let Block::If { .. } = block { "end" } else { "again" };
+            return Err(self.error(format!("missing {} at end of file", missing)));
+        }
+
+        let main = *self.labels.get("main").ok_or_else(|| self.error("program has no main label"))?;
+        self.patch_jump(PROGRAM_START_ADDR, main as usize)?;
+
+        for fixup in std::mem::take(&mut self.fixups) {
+            self.line = fixup.line;
+            let target = *self.labels.get(&fixup.label).ok_or_else(|| self.error(format!("undefined name {}", fixup.label)))?;
+            match fixup.kind {
+                FixupKind::Address => self.patch_jump(fixup.addr, target as usize)?,
+                FixupKind::Long => self.write_word(fixup.addr + 2, target),
+            }
+        }
+
+        let mut symbols = Symbols::new();
+        for (name, addr) in &self.labels {
+            symbols.insert(name, *addr);
+        }
+
+        Ok(Assembly { rom: self.rom, symbols })
+    }
+
+    fn error(&self, message: impl Into<String>) -> AsmError {
+        AsmError { file: self.file.clone(), line: self.line, message: message.into() }
+    }
+
+    fn next(&mut self) -> Result<String, AsmError> {
+        let token = self.tokens.pop().ok_or_else(|| self.error("unexpected end of file"))?;
+        self.line = token.line;
+
+        Ok(token.text)
+    }
+
+    fn peek(&self) -> Option<&str> {
+        self.tokens.last().map(|token| token.text.as_str())
+    }
+
+    fn expect(&mut self, expected: &str) -> Result<(), AsmError> {
+        let token = self.next()?;
+        if token != expected {
+            return Err(self.error(format!("expected {}, found {}", expected, token)));
+        }
+
+        Ok(())
+    }
+
+    fn statement(&mut self, token: &str) -> Result<(), AsmError> {
+        match token {
+            ":" => {
+                let name = self.next()?;
+                self.define_label(&name)?;
+            }
+            ":alias" => {
+                let name = self.next()?;
+                let x = self.register()?;
+                self.aliases.insert(name, x);
+            }
+            ":const" => {
+                let name = self.next()?;
+                let value = self.value()?;
+                self.define_constant(&name, value)?;
+            }
+            ":calc" => {
+                let name = self.next()?;
+                self.expect("{")?;
+                let value = self.calc()?;
+                self.expect("}")?;
+                self.define_constant(&name, value)?;
+            }
+            ":macro" => self.define_macro()?,
+            ":byte" => {
+                let value = if self.peek() == Some("{") {
+                    self.next()?;
+                    let value = self.calc()?;
+                    self.expect("}")?;
+                    value
+                } else {
+                    self.value()?
+                };
+                let byte = self.to_byte(value)?;
+                self.emit_byte(byte)?;
+            }
+            ":org" => {
+                let addr = self.value()? as i64;
+                if addr < PROGRAM_START_ADDR as i64 || addr >= MEMORY_SIZE as i64 {
+                    return Err(self.error(format!(":org {:X} is outside program memory", addr)));
+                }
+                self.addr = addr as usize;
+            }
+            ":call" => {
+                let nnn = self.address(FixupKind::Address)?;
+                self.emit(Instruction::Call { nnn })?;
+            }
+            // Debugger hints for Octo's own IDE
+            ":breakpoint" => {
+                self.next()?;
+            }
+            ":monitor" => {
+                self.next()?;
+                self.next()?;
+            }
+
+            "clear" => self.emit(Instruction::Cls)?,
+            "return" | ";" => self.emit(Instruction::Ret)?,
+            "exit" => self.emit(Instruction::Exit)?,
+            "hires" => self.emit(Instruction::HighRes)?,
+            "lores" => self.emit(Instruction::LowRes)?,
+            "scroll-down" => {
+                let n = self.nibble()?;
+                self.emit(Instruction::ScrollDown { n })?;
+            }
+            "scroll-left" => self.emit(Instruction::ScrollLeft)?,
+            "scroll-right" => self.emit(Instruction::ScrollRight)?,
+            "audio" => self.emit(Instruction::LoadAudio)?,
+            "plane" => {
+                let n = self.nibble()?;
+                self.emit(Instruction::SelectPlane { n })?;
+            }
+            "bcd" => {
+                let x = self.register()?;
+                self.emit(Instruction::StoreBcd { x })?;
+            }
+            "save" | "load" => {
+                let x = self.register()?;
+                let instruction = if self.peek() == Some("-") {
+                    self.next()?;
+                    let y = self.register()?;
+                    if token == "save" { Instruction::SaveRange { x, y } } else { Instruction::LoadRange { x, y } }
+                } else if token == "save" {
+                    Instruction::StoreRegs { x }
+                } else {
+                    Instruction::LoadRegs { x }
+                };
+                self.emit(instruction)?;
+            }
+            "saveflags" => {
+                let x = self.register()?;
+                self.emit(Instruction::StoreFlags { x })?;
+            }
+            "loadflags" => {
+                let x = self.register()?;
+                self.emit(Instruction::LoadFlags { x })?;
+            }
+            "sprite" => {
+                let x = self.register()?;
+                let y = self.register()?;
+                let n = self.nibble()?;
+                self.emit(Instruction::Draw { x, y, n })?;
+            }
+            "jump" => {
+                let nnn = self.address(FixupKind::Address)?;
+                self.emit(Instruction::Jump { nnn })?;
+            }
+            "jump0" => {
+                let nnn = self.address(FixupKind::Address)?;
+                self.emit(Instruction::JumpV0 { nnn })?;
+            }
+            "delay" | "buzzer" | "pitch" => {
+                self.expect(":=")?;
+                let x = self.register()?;
+                let instruction = match token {
+                    "delay" => Instruction::SetDelay { x },
+                    "buzzer" => Instruction::SetSound { x },
+                    _ => Instruction::SetPitch { x },
+                };
+                self.emit(instruction)?;
+            }
+            "i" => self.index()?,
+
+            "if" => {
+                let condition = self.condition()?;
+                match self.next()?.as_str() {
+                    "then" => self.skip(condition, false)?,
+                    "begin" => {
+                        self.skip(condition, true)?;
+                        let jump = self.addr;
+                        self.emit(Instruction::Jump { nnn: 0 })?;
+                        self.blocks.push(Block::If { jump, has_else: false });
+                    }
+                    other => return Err(self.error(format!("expected then or begin, found {}", other))),
+                }
+            }
+            "else" => match self.blocks.pop() {
+                Some(Block::If { jump, has_else: false }) => {
+                    let end_jump = self.addr;
+                    self.emit(Instruction::Jump { nnn: 0 })?;
+                    self.patch_jump(jump, self.addr)?;
+                    self.blocks.push(Block::If { jump: end_jump, has_else: true });
+                }
+                _ => return Err(self.error("else without if ... begin")),
+            },
+            "end" => match self.blocks.pop() {
+                Some(Block::If { jump, .. }) => self.patch_jump(jump, self.addr)?,
+                _ => return Err(self.error("end without if ... begin")),
+            },
+            "loop" => self.blocks.push(Block::Loop { start: self.addr, breaks: Vec::new() }),
+            "while" => {
+                let condition = self.condition()?;
+                self.skip(condition, true)?;
+                let jump = self.addr;
+                self.emit(Instruction::Jump { nnn: 0 })?;
+                match self.blocks.iter_mut().rev().find(|block| matches!(block, Block::Loop { .. })) {
+                    Some(Block::Loop { breaks, .. }) => breaks.push(jump),
+                    _ => return Err(self.error("while outside of a loop")),
+                }
+            }
+            "again" => match self.blocks.pop() {
+                Some(Block::Loop { start, breaks }) => {
+                    let jump = self.addr;
+                    self.emit(Instruction::Jump { nnn: 0 })?;
+                    self.patch_jump(jump, start)?;
+                    for jump in breaks {
+                        self.patch_jump(jump, self.addr)?;
+                    }
+                }
+                _ => return Err(self.error("again without loop")),
+            },
+
+            _ if self.is_register(token) => {
+                let x = self.register_named(token)?;
+                self.assignment(x)?;
+            }
+            _ if self.macros.contains_key(token) => self.expand(token)?,
+            _ if parse_number(token).is_some() || self.constants.contains_key(token) => {
+                let value = self.lookup(token)?;
+                let byte = self.to_byte(value)?;
+                self.emit_byte(byte)?;
+            }
+            // Anything else is a subroutine, possibly defined further down
+            _ => {
+                self.tokens.push(Token { text: String::from(token), line: self.line });
+                let nnn = self.address(FixupKind::Address)?;
+                self.emit(Instruction::Call { nnn })?;
+            }
+        }
+
+        Ok(())
+    }
+
+    // vx followed by an operator
+    fn assignment(&mut self, x: usize) -> Result<(), AsmError> {
+        let op = self.next()?;
+
+        let instruction = match op.as_str() {
+            ":=" => match self.peek() {
+                Some("random") => {
+                    self.next()?;
+                    Instruction::Random { x, nn: self.byte()? }
+                }
+                Some("key") => {
+                    self.next()?;
+                    Instruction::WaitKey { x }
+                }
+                Some("delay") => {
+                    self.next()?;
+                    Instruction::LoadDelay { x }
+                }
+                _ => match self.operand()? {
+                    Operand::Register(y) => Instruction::LoadReg { x, y },
+                    Operand::Byte(nn) => Instruction::LoadImm { x, nn },
+                },
+            },
+            "+=" => match self.operand()? {
+                Operand::Register(y) => Instruction::AddReg { x, y },
+                Operand::Byte(nn) => Instruction::AddImm { x, nn },
+            },
+            "-=" => match self.operand()? {
+                Operand::Register(y) => Instruction::SubReg { x, y },
+                // There is no subtract immediate, add the two's complement instead
+                Operand::Byte(nn) => Instruction::AddImm { x, nn: nn.wrapping_neg() },
+            },
+            "=-" => Instruction::SubNReg { x, y: self.register()? },
+            "|=" => Instruction::Or { x, y: self.register()? },
+            "&=" => Instruction::And { x, y: self.register()? },
+            "^=" => Instruction::Xor { x, y: self.register()? },
+            ">>=" => Instruction::ShiftRight { x, y: self.register()? },
+            "<<=" => Instruction::ShiftLeft { x, y: self.register()? },
+            _ => return Err(self.error(format!("unknown operator {}", op))),
+        };
+
+        self.emit(instruction)
+    }
+
+    // i := address, i := long address, i := hex vx, i := bighex vx or i += vx
+    fn index(&mut self) -> Result<(), AsmError> {
+        let op = self.next()?;
+
+        match op.as_str() {
+            "+=" => {
+                let x = self.register()?;
+                self.emit(Instruction::AddIndex { x })
+            }
+            ":=" => match self.peek() {
+                Some("long") => {
+                    self.next()?;
+                    let nnnn = self.address(FixupKind::Long)?;
+                    self.emit(Instruction::LoadIndexLong)?;
+                    self.emit_byte((nnnn >> 8) as u8)?;
+                    self.emit_byte(nnnn as u8)
+                }
+                Some("hex") => {
+                    self.next()?;
+                    let x = self.register()?;
+                    self.emit(Instruction::LoadFont { x })
+                }
+                Some("bighex") => {
+                    self.next()?;
+                    let x = self.register()?;
+                    self.emit(Instruction::LoadBigFont { x })
+                }
+                _ => {
+                    let nnn = self.address(FixupKind::Address)?;
+                    self.emit(Instruction::LoadIndex { nnn })
+                }
+            },
+            _ => Err(self.error(format!("unknown operator i {}", op))),
+        }
+    }
+
+    fn condition(&mut self) -> Result<(usize, String, Option<Operand>), AsmError> {
+        let x = self.register()?;
+        let op = self.next()?;
+
+        match op.as_str() {
+            "key" | "-key" => Ok((x, op, None)),
+            "==" | "!=" | "<" | ">" | "<=" | ">=" => {
+                let rhs = self.operand()?;
+                Ok((x, op, Some(rhs)))
+            }
+            _ => Err(self.error(format!("unknown comparison {}", op))),
+        }
+    }
+
+    // Emit instructions that skip the next one when the condition's result equals `when`
+    fn skip(&mut self, condition: (usize, String, Option<Operand>), when: bool) -> Result<(), AsmError> {
+        let (x, op, rhs) = condition;
+
+        let instruction = match (op.as_str(), rhs) {
+            ("key", _) | ("-key", _) => {
+                if (op == "key") == when {
+                    Instruction::SkipKeyPressed { x }
+                } else {
+                    Instruction::SkipKeyNotPressed { x }
+                }
+            }
+            ("==", Some(rhs)) | ("!=", Some(rhs)) => match (rhs, (op == "==") == when) {
+                (Operand::Register(y), true) => Instruction::SkipEqReg { x, y },
+                (Operand::Register(y), false) => Instruction::SkipNeReg { x, y },
+                (Operand::Byte(nn), true) => Instruction::SkipEqImm { x, nn },
+                (Operand::Byte(nn), false) => Instruction::SkipNeImm { x, nn },
+            },
+            (_, Some(rhs)) => {
+                // vf := left - right leaves the carry in vf, 1 when left >= right
+                let lhs = Operand::Register(x);
+                let (left, right, flag) = match op.as_str() {
+                    ">" => (rhs, lhs, 0),
+                    "<" => (lhs, rhs, 0),
+                    ">=" => (lhs, rhs, 1),
+                    _ => (rhs, lhs, 1),
+                };
+                match (left, right) {
+                    (Operand::Register(a), Operand::Register(b)) => {
+                        self.emit(Instruction::LoadReg { x: 0xF, y: a })?;
+                        self.emit(Instruction::SubReg { x: 0xF, y: b })?;
+                    }
+                    (Operand::Register(a), Operand::Byte(nn)) => {
+                        self.emit(Instruction::LoadImm { x: 0xF, nn })?;
+                        self.emit(Instruction::SubNReg { x: 0xF, y: a })?;
+                    }
+                    (Operand::Byte(nn), Operand::Register(b)) => {
+                        self.emit(Instruction::LoadImm { x: 0xF, nn })?;
+                        self.emit(Instruction::SubReg { x: 0xF, y: b })?;
+                    }
+                    (Operand::Byte(_), Operand::Byte(_)) => unreachable!("the left side is always a register"),
+                }
+                if when {
+                    Instruction::SkipEqImm { x: 0xF, nn: flag }
+                } else {
+                    Instruction::SkipNeImm { x: 0xF, nn: flag }
+                }
+            }
+            _ => unreachable!("comparisons always have a right hand side"),
+        };
+
+        self.emit(instruction)
+    }
+
+    fn define_label(&mut self, name: &str) -> Result<(), AsmError> {
+        if self.labels.contains_key(name) || self.constants.contains_key(name) {
+            return Err(self.error(format!("{} is already defined", name)));
+        }
+
+        self.labels.insert(String::from(name), self.addr as u16);
+        Ok(())
+    }
+
+    // :calc may redefine a constant, but never a label
+    fn define_constant(&mut self, name: &str, value: f64) -> Result<(), AsmError> {
+        if self.labels.contains_key(name) {
+            return Err(self.error(format!("{} is already defined", name)));
+        }
+
+        self.constants.insert(String::from(name), value);
+        Ok(())
+    }
+
+    // :macro name args... { body }
+    fn define_macro(&mut self) -> Result<(), AsmError> {
+        let name = self.next()?;
+
+        let mut args = Vec::new();
+        loop {
+            let token = self.next()?;
+            if token == "{" {
+                break;
+            }
+            args.push(token);
+        }
+
+        let mut body = Vec::new();
+        let mut depth = 1;
+        loop {
+            let token = self.tokens.pop().ok_or_else(|| self.error(format!("macro {} is missing its }}", name)))?;
+            match token.text.as_str() {
+                "{" => depth += 1,
+                "}" => depth -= 1,
+                _ => (),
+            }
+            if depth == 0 {
+                break;
+            }
+            body.push(token);
+        }
+
+        self.macros.insert(name, Macro { args, body });
+        Ok(())
+    }
+
+    // Replace a macro invocation with its body, arguments substituted
+    fn expand(&mut self, name: &str) -> Result<(), AsmError> {
+        self.expansions += 1;
+        if self.expansions > MAX_EXPANSIONS {
+            return Err(self.error(format!("too many macro expansions, does {} invoke itself?", name)));
+        }
+
+        let arg_count = self.macros[name].args.len();
+        let mut values = Vec::new();
+        for _ in 0..arg_count {
+            values.push(self.next()?);
+        }
+
+        let definition = &self.macros[name];
+        let line = self.line;
+        let expanded: Vec<Token> = definition
+            .body
+            .iter()
+            .rev()
+            .map(|token| {
+                let text = match definition.args.iter().position(|arg| *arg == token.text) {
+                    Some(i) => values[i].clone(),
+                    None => token.text.clone(),
+                };
+                // Errors inside the expansion point at the invocation
+                Token { text, line }
+            })
+            .collect();
+        self.tokens.extend(expanded);
+
+        Ok(())
+    }
+
+    /*
+        :calc expressions. Like Octo there is no precedence, everything is
+        evaluated right to left, so `2 * 3 + 4` is 14. Use parentheses to
+        group, with spaces around them.
+    */
+
+    fn calc(&mut self) -> Result<f64, AsmError> {
+        let left = self.calc_term()?;
+
+        match self.peek() {
+            Some("}") | Some(")") | None => Ok(left),
+            Some(_) => {
+                let op = self.next()?;
+                let right = self.calc()?;
+                let (a, b) = (left, right);
+                let int = |value: f64| value as i64;
+                let shift = |op: fn(i64, u32) -> Option<i64>| match u32::try_from(int(b)).ok().and_then(|by| op(int(a), by)) {
+                    Some(value) => Ok(value as f64),
+                    None => Err(self.error("shift out of range")),
+                };
+
+                let value = match op.as_str() {
+                    "+" => a + b,
+                    "-" => a - b,
+                    "*" => a * b,
+                    "/" | "%" if b == 0.0 => return Err(self.error("division by zero")),
+                    "/" => a / b,
+                    "%" => a % b,
+                    "&" => (int(a) & int(b)) as f64,
+                    "|" => (int(a) | int(b)) as f64,
+                    "^" => (int(a) ^ int(b)) as f64,
+                    "<<" => shift(i64::checked_shl)?,
+                    ">>" => shift(i64::checked_shr)?,
+                    "min" => a.min(b),
+                    "max" => a.max(b),
+                    "<" => (a < b) as i64 as f64,
+                    ">" => (a > b) as i64 as f64,
+                    "<=" => (a <= b) as i64 as f64,
+                    ">=" => (a >= b) as i64 as f64,
+                    "==" => (a == b) as i64 as f64,
+                    "!=" => (a != b) as i64 as f64,
+                    _ => return Err(self.error(format!("unknown operator {}", op))),
+                };
+                Ok(value)
+            }
+        }
+    }
+
+    fn calc_term(&mut self) -> Result<f64, AsmError> {
+        let token = self.next()?;
+
+        let value = match token.as_str() {
+            "(" => {
+                let value = self.calc()?;
+                self.expect(")")?;
+                value
+            }
+            "-" => -self.calc_term()?,
+            "~" => !(self.calc_term()? as i64) as f64,
+            "!" => (self.calc_term()? == 0.0) as i64 as f64,
+            "abs" => self.calc_term()?.abs(),
+            "floor" => self.calc_term()?.floor(),
+            "ceil" => self.calc_term()?.ceil(),
+            "sqrt" => self.calc_term()?.sqrt(),
+            "sin" => self.calc_term()?.sin(),
+            "cos" => self.calc_term()?.cos(),
+            // The byte already compiled at an address
+            "@" => {
+                let addr = self.calc_term()? as usize;
+                let offset = addr.wrapping_sub(PROGRAM_START_ADDR);
+                *self.rom.get(offset).ok_or_else(|| self.error(format!("@ {:X} is outside the program", addr)))? as f64
+            }
+            "HERE" => self.addr as f64,
+            "PI" => std::f64::consts::PI,
+            "E" => std::f64::consts::E,
+            _ => self.lookup(&token)?,
+        };
+
+        Ok(value)
+    }
+
+    // A number, constant or already defined label
+    fn lookup(&self, token: &str) -> Result<f64, AsmError> {
+        if let Some(value) = parse_number(token) {
+            return Ok(value);
+        }
+        if let Some(value) = self.constants.get(token) {
+            return Ok(*value);
+        }
+        if let Some(addr) = self.labels.get(token) {
+            return Ok(*addr as f64);
+        }
+
+        Err(self.error(format!("undefined name {}", token)))
+    }
+
+    fn value(&mut self) -> Result<f64, AsmError> {
+        let token = self.next()?;
+        self.lookup(&token)
+    }
+
+    fn to_byte(&self, value: f64) -> Result<u8, AsmError> {
+        let value = value.floor() as i64;
+        if !(-0x80..=0xFF).contains(&value) {
+            return Err(self.error(format!("{} does not fit in a byte", value)));
+        }
+
+        Ok(value as u8)
+    }
+
+    fn byte(&mut self) -> Result<u8, AsmError> {
+        let value = self.value()?;
+        self.to_byte(value)
+    }
+
+    fn nibble(&mut self) -> Result<u8, AsmError> {
+        let value = self.value()?.floor() as i64;
+        if !(0..=0xF).contains(&value) {
+            return Err(self.error(format!("{} does not fit in a nibble", value)));
+        }
+
+        Ok(value as u8)
+    }
+
+    // An address operand. Labels that are not defined yet become fixups
+    // against the instruction about to be emitted at the current address
+    fn address(&mut self, kind: FixupKind) -> Result<u16, AsmError> {
+        let token = self.next()?;
+        let max = match kind {
+            FixupKind::Address => 0xFFF,
+            FixupKind::Long => 0xFFFF,
+        };
+
+        if parse_number(&token).is_none() && !self.constants.contains_key(&token) && !self.labels.contains_key(&token) {
+            self.fixups.push(Fixup { addr: self.addr, kind, label: token, line: self.line });
+            return Ok(0);
+        }
+
+        let value = self.lookup(&token)?.floor() as i64;
+        if !(0..=max).contains(&value) {
+            return Err(self.error(format!("address {:X} is out of range", value)));
+        }
+
+        Ok(value as u16)
+    }
+
+    fn operand(&mut self) -> Result<Operand, AsmError> {
+        match self.peek() {
+            Some(token) if self.is_register(token) => Ok(Operand::Register(self.register()?)),
+            _ => Ok(Operand::Byte(self.byte()?)),
+        }
+    }
+
+    fn is_register(&self, token: &str) -> bool {
+        self.aliases.contains_key(token) || parse_register(token).is_some()
+    }
+
+    fn register(&mut self) -> Result<usize, AsmError> {
+        let token = self.next()?;
+        self.register_named(&token)
+    }
+
+    fn register_named(&self, token: &str) -> Result<usize, AsmError> {
+        self.aliases
+            .get(token)
+            .copied()
+            .or_else(|| parse_register(token))
+            .ok_or_else(|| self.error(format!("expected a register, found {}", token)))
+    }
+
+    fn emit(&mut self, instruction: Instruction) -> Result<(), AsmError> {
+        let [hi, lo] = instruction.encode().to_be_bytes();
+        self.emit_byte(hi)?;
+        self.emit_byte(lo)
+    }
+
+    fn emit_byte(&mut self, byte: u8) -> Result<(), AsmError> {
+        if self.addr >= MEMORY_SIZE {
+            return Err(self.error("program does not fit in memory"));
+        }
+
+        let offset = self.addr - PROGRAM_START_ADDR;
+        if offset >= self.rom.len() {
+            self.rom.resize(offset + 1, 0);
+        }
+        self.rom[offset] = byte;
+        self.addr += 1;
+
+        Ok(())
+    }
+
+    fn write_word(&mut self, addr: usize, word: u16) {
+        let offset = addr - PROGRAM_START_ADDR;
+        self.rom[offset..offset + 2].copy_from_slice(&word.to_be_bytes());
+    }
+
+    // Fill in the address of a jump, call or I load emitted earlier
+    fn patch_jump(&mut self, addr: usize, target: usize) -> Result<(), AsmError> {
+        if target > 0xFFF {
+            return Err(self.error(format!("{:X} is out of reach, use i := long", target)));
+        }
+
+        let offset = addr - PROGRAM_START_ADDR;
+        let opcode = u16::from_be_bytes([self.rom[offset], self.rom[offset + 1]]);
+        self.write_word(addr, (opcode & 0xF000) | target as u16);
+
+        Ok(())
+    }
+}
+
+fn parse_register(token: &str) -> Option<usize> {
+    let digit = token.strip_prefix('v').or_else(|| token.strip_prefix('V'))?;
+    if digit.len() != 1 {
+        return None;
+    }
+
+    usize::from_str_radix(digit, 16).ok()
+}
+
+fn parse_number(token: &str) -> Option<f64> {
+    let (negative, digits) = match token.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, token),
+    };
+
+    let value = if let Some(hex) = digits.strip_prefix("0x").or_else(|| digits.strip_prefix("0X")) {
+        i64::from_str_radix(hex, 16).ok()? as f64
+    } else if let Some(binary) = digits.strip_prefix("0b").or_else(|| digits.strip_prefix("0B")) {
+        i64::from_str_radix(binary, 2).ok()? as f64
+    } else if digits.starts_with(|c: char| c.is_ascii_digit()) {
+        digits.parse().ok()?
+    } else {
+        return None;
+    };
+
+    Some(if negative { -value } else { value })
+}
@@ -0,0 +1,114 @@
+use alloc::format;
+use alloc::string::String;
+use core::str::FromStr;
+
+/*
+    Interpreters written after the COSMAC VIP disagree on what a handful of
+    opcodes should do. ROMs tend to rely on the behaviour of whichever
+    interpreter their author used, so the differences are configurable.
+*/
+
+// How far Fx55/Fx65 move I after storing or loading registers
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IndexIncrement {
+    None,       // I is left unchanged (SUPER-CHIP 1.1)
+    X,          // I += X (CHIP-48)
+    XPlusOne,   // I += X + 1 (COSMAC VIP, XO-CHIP)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Quirks {
+    // 8xy6/8xyE shift VY into VX instead of shifting VX in place
+    pub shift_uses_vy: bool,
+    pub index_increment: IndexIncrement,
+    // Bnnn behaves as Bxnn and jumps to xnn + VX instead of nnn + V0
+    pub jump_uses_vx: bool,
+    // 8xy1/8xy2/8xy3 reset VF to 0
+    pub logic_resets_vf: bool,
+    // Sprites are cut off at the screen edge instead of wrapping around
+    pub clip_sprites: bool,
+    // XO-CHIP extensions: 64K of RAM, two bitplanes and the audio pattern buffer
+    pub xo_chip: bool,
+}
+
+impl Quirks {
+    pub const NAMES: [&'static str; 5] = ["modern", "vip", "chip48", "schip", "xochip"];
+
+    // What most emulators written from Cowgod's reference do, and what this
+    // one did before there were profiles. The default
+    pub fn modern() -> Self {
+        Self {
+            shift_uses_vy: false,
+            index_increment: IndexIncrement::None,
+            jump_uses_vx: false,
+            logic_resets_vf: false,
+            clip_sprites: false,
+            xo_chip: false,
+        }
+    }
+
+    pub fn vip() -> Self {
+        Self {
+            shift_uses_vy: true,
+            index_increment: IndexIncrement::XPlusOne,
+            jump_uses_vx: false,
+            logic_resets_vf: true,
+            clip_sprites: true,
+            xo_chip: false,
+        }
+    }
+
+    pub fn chip48() -> Self {
+        Self {
+            shift_uses_vy: false,
+            index_increment: IndexIncrement::X,
+            jump_uses_vx: true,
+            logic_resets_vf: false,
+            clip_sprites: true,
+            xo_chip: false,
+        }
+    }
+
+    pub fn schip() -> Self {
+        Self {
+            shift_uses_vy: false,
+            index_increment: IndexIncrement::None,
+            jump_uses_vx: true,
+            logic_resets_vf: false,
+            clip_sprites: true,
+            xo_chip: false,
+        }
+    }
+
+    pub fn xochip() -> Self {
+        Self {
+            shift_uses_vy: true,
+            index_increment: IndexIncrement::XPlusOne,
+            jump_uses_vx: false,
+            logic_resets_vf: false,
+            clip_sprites: false,
+            xo_chip: true,
+        }
+    }
+}
+
+impl Default for Quirks {
+    fn default() -> Self {
+        Self::modern()
+    }
+}
+
+impl FromStr for Quirks {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name.to_ascii_lowercase().as_str() {
+            "modern" => Ok(Self::modern()),
+            "vip" | "chip8" | "chip-8" => Ok(Self::vip()),
+            "chip48" | "chip-48" => Ok(Self::chip48()),
+            "schip" | "superchip" | "schip1.1" => Ok(Self::schip()),
+            "xochip" | "xo-chip" => Ok(Self::xochip()),
+            _ => Err(format!("unknown quirks profile '{}', expected one of: {}", name, Self::NAMES.join(", "))),
+        }
+    }
+}
@@ -0,0 +1,165 @@
+/*
+    What the speaker should be doing. Plain CHIP-8 only has a buzzer that
+    sounds while the sound timer is non-zero, its pitch and timbre are up to
+    the frontend. XO-CHIP programs can instead load a 128 bit sample pattern
+    with F002 and set its playback rate with Fx3A, which the buzzer then
+    plays on a loop.
+*/
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::f32::consts::PI;
+use core::mem;
+use core::str::FromStr;
+
+pub const PATTERN_BITS: usize = 128;
+// Pitch of a plain beep unless the frontend picks another
+pub const DEFAULT_FREQUENCY: f32 = 440.0;
+const FRAMES_PER_SECOND: u32 = 60;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Tone {
+    Silent,
+    Beep,
+    // One bit per sample, most significant bit first, `rate` samples per second
+    Pattern { pattern: [u8; PATTERN_BITS / 8], rate: f32 },
+}
+
+// XO-CHIP's Fx3A: pitch 64 plays 4000 samples per second, every 48 steps doubles it
+pub fn pattern_rate(pitch: u8) -> f32 {
+    let steps = pitch as i32 - 64;
+    let octaves = steps.div_euclid(48);
+    let fraction = steps.rem_euclid(48) as f32 / 48.0;
+
+    let rate = 4000.0 * exp2_fraction(fraction);
+    if octaves >= 0 {
+        rate * (1 << octaves) as f32
+    } else {
+        rate / (1 << -octaves) as f32
+    }
+}
+
+// 2^x for x in [0, 1). core has no powf without std, and a polynomial is
+// accurate to well under a cent here
+fn exp2_fraction(x: f32) -> f32 {
+    use core::f32::consts::LN_2;
+
+    1.0 + x * (LN_2 + x * (0.240_226_5 + x * (0.055_504_1 + x * (0.009_618_1 + x * 0.001_333_3))))
+}
+
+// Shape of a plain beep. Patterns are always played as a square wave
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Wave {
+    Square,
+    Sine,
+    Triangle,
+}
+
+impl Wave {
+    pub const NAMES: [&'static str; 3] = ["square", "sine", "triangle"];
+
+    // `phase` is the position within one period, 0 to 1
+    fn sample(self, phase: f32) -> f32 {
+        match self {
+            Wave::Square => if phase < 0.5 { 1.0 } else { -1.0 },
+            Wave::Sine => if phase < 0.5 { half_sine(phase * 2.0 * PI) } else { -half_sine((phase - 0.5) * 2.0 * PI) },
+            Wave::Triangle => 1.0 - 4.0 * (phase - 0.5).abs(),
+        }
+    }
+}
+
+impl FromStr for Wave {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name.to_ascii_lowercase().as_str() {
+            "square" => Ok(Wave::Square),
+            "sine" => Ok(Wave::Sine),
+            "triangle" => Ok(Wave::Triangle),
+            _ => Err(format!("unknown waveform '{}', expected one of: {}", name, Self::NAMES.join(", "))),
+        }
+    }
+}
+
+// sin(x) for x in [0, pi], by Bhaskara's approximation. Again no sin in
+// core, and the error is inaudible
+fn half_sine(x: f32) -> f32 {
+    let product = x * (PI - x);
+    16.0 * product / (5.0 * PI * PI - 4.0 * product)
+}
+
+/*
+    Turns the tone into samples between -1 and 1 at any sample rate. The
+    SDL frontend feeds it from its audio callback, the headless runner
+    renders one frame's worth at a time to write a WAV file.
+*/
+pub struct Renderer {
+    tone: Tone,
+    wave: Wave,
+    frequency: f32,
+    sample_rate: u32,
+    // Position within the current period for beeps, or in bits through the pattern
+    phase: f32,
+    // Samples rendered by render_frame so far, to keep whole frames in step with the clock
+    frame_samples: u64,
+    frames: u64,
+}
+
+impl Renderer {
+    pub fn new(sample_rate: u32, wave: Wave, frequency: f32) -> Self {
+        Self {
+            tone: Tone::Silent,
+            wave,
+            frequency,
+            sample_rate,
+            phase: 0.0,
+            frame_samples: 0,
+            frames: 0,
+        }
+    }
+
+    pub fn set_tone(&mut self, tone: Tone) {
+        // Start each new beep at the top of the wave, and patterns at their first bit
+        if mem::discriminant(&self.tone) != mem::discriminant(&tone) {
+            self.phase = 0.0;
+        }
+        self.tone = tone;
+    }
+
+    pub fn render(&mut self, out: &mut [f32]) {
+        let sample_rate = self.sample_rate as f32;
+
+        for sample in out.iter_mut() {
+            *sample = match self.tone {
+                Tone::Silent => 0.0,
+                Tone::Beep => {
+                    let value = self.wave.sample(self.phase);
+                    self.phase = (self.phase + self.frequency / sample_rate) % 1.0;
+                    value
+                }
+                Tone::Pattern { pattern, rate } => {
+                    let bit = self.phase as usize % PATTERN_BITS;
+                    let value = if pattern[bit / 8] & (0x80 >> (bit % 8)) != 0 { 1.0 } else { -1.0 };
+                    self.phase = (self.phase + rate / sample_rate) % PATTERN_BITS as f32;
+                    value
+                }
+            };
+        }
+    }
+
+    // Appends a 60th of a second of `tone`. Sample rates that don't divide
+    // by 60 get frames a sample longer now and then so they never drift
+    pub fn render_frame(&mut self, tone: Tone, out: &mut Vec<f32>) {
+        self.set_tone(tone);
+
+        self.frames += 1;
+        let total = self.frames * self.sample_rate as u64 / FRAMES_PER_SECOND as u64;
+        let count = (total - self.frame_samples) as usize;
+        self.frame_samples = total;
+
+        let start = out.len();
+        out.resize(start + count, 0.0);
+        self.render(&mut out[start..]);
+    }
+}
@@ -0,0 +1,127 @@
+use alloc::vec::Vec;
+
+use crate::error::EmulatorError;
+
+/*
+    Helpers for the save state format. Everything is written in order with
+    no padding, multi-byte values are little endian:
+
+        "C8ST"  magic
+        u8      format version
+        ...     emulator fields, see Emulator::save_state
+*/
+
+pub const STATE_MAGIC: &[u8; 4] = b"C8ST";
+pub const STATE_VERSION: u8 = 3;
+
+pub struct StateWriter {
+    buf: Vec<u8>,
+}
+
+impl StateWriter {
+    pub fn new() -> Self {
+        let mut buf = Vec::new();
+        buf.extend_from_slice(STATE_MAGIC);
+        buf.push(STATE_VERSION);
+
+        Self { buf }
+    }
+
+    pub fn u8(&mut self, value: u8) {
+        self.buf.push(value);
+    }
+
+    pub fn bool(&mut self, value: bool) {
+        self.buf.push(value as u8);
+    }
+
+    pub fn u16(&mut self, value: u16) {
+        self.buf.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn u32(&mut self, value: u32) {
+        self.buf.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn u64(&mut self, value: u64) {
+        self.buf.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn bytes(&mut self, bytes: &[u8]) {
+        self.buf.extend_from_slice(bytes);
+    }
+
+    pub fn finish(self) -> Vec<u8> {
+        self.buf
+    }
+}
+
+pub struct StateReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> StateReader<'a> {
+    // Checks the header and leaves the reader positioned at the first field
+    pub fn new(data: &'a [u8]) -> Result<Self, EmulatorError> {
+        let mut reader = Self { data, pos: 0 };
+
+        if reader.take(STATE_MAGIC.len())? != STATE_MAGIC {
+            return Err(EmulatorError::InvalidState("not a save state"));
+        }
+        if reader.u8()? != STATE_VERSION {
+            return Err(EmulatorError::InvalidState("unsupported save state version"));
+        }
+
+        Ok(reader)
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], EmulatorError> {
+        let bytes = self.data
+            .get(self.pos..self.pos + len)
+            .ok_or(EmulatorError::InvalidState("save state is truncated"))?;
+        self.pos += len;
+
+        Ok(bytes)
+    }
+
+    pub fn u8(&mut self) -> Result<u8, EmulatorError> {
+        Ok(self.take(1)?[0])
+    }
+
+    pub fn bool(&mut self) -> Result<bool, EmulatorError> {
+        Ok(self.u8()? != 0)
+    }
+
+    pub fn u16(&mut self) -> Result<u16, EmulatorError> {
+        Ok(u16::from_le_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    pub fn u32(&mut self) -> Result<u32, EmulatorError> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    pub fn u64(&mut self) -> Result<u64, EmulatorError> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    pub fn bytes(&mut self, out: &mut [u8]) -> Result<(), EmulatorError> {
+        out.copy_from_slice(self.take(out.len())?);
+
+        Ok(())
+    }
+
+    // Borrowed rather than copied, for buffers too large to keep a spare of
+    pub fn slice(&mut self, len: usize) -> Result<&'a [u8], EmulatorError> {
+        self.take(len)
+    }
+
+    // Fails if anything is left over, which means the state came from a different layout
+    pub fn finish(self) -> Result<(), EmulatorError> {
+        if self.pos != self.data.len() {
+            return Err(EmulatorError::InvalidState("save state has trailing data"));
+        }
+
+        Ok(())
+    }
+}
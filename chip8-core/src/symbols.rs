@@ -0,0 +1,74 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+/*
+    Names for addresses in a program, written by the assembler and read by
+    the debugger. The file format is one symbol per line, a hex address
+    followed by the name:
+
+        0x0200 start
+        0x023A draw_player
+
+    Blank lines and anything after a `;` are ignored.
+*/
+
+#[derive(Debug, Clone, Default)]
+pub struct Symbols {
+    // Where several names share an address the first one defined is kept here
+    names: BTreeMap<u16, String>,
+    addresses: HashMap<String, u16>,
+}
+
+impl Symbols {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut symbols = Self::new();
+
+        for (i, line) in text.lines().enumerate() {
+            let line = line.split(';').next().unwrap().trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let (addr, name) = line
+                .split_once(char::is_whitespace)
+                .ok_or_else(|| format!("line {}: expected an address and a name", i + 1))?;
+            let digits = addr.trim_start_matches("0x").trim_start_matches("0X");
+            let addr = u16::from_str_radix(digits, 16).map_err(|_| format!("line {}: invalid address {}", i + 1, addr))?;
+
+            symbols.insert(name.trim(), addr);
+        }
+
+        Ok(symbols)
+    }
+
+    pub fn insert(&mut self, name: &str, addr: u16) {
+        self.names.entry(addr).or_insert_with(|| String::from(name));
+        self.addresses.insert(String::from(name), addr);
+    }
+
+    pub fn name(&self, addr: u16) -> Option<&str> {
+        self.names.get(&addr).map(|name| name.as_str())
+    }
+
+    pub fn address(&self, name: &str) -> Option<u16> {
+        self.addresses.get(name).copied()
+    }
+}
+
+// Writes the file format above, sorted by address
+impl fmt::Display for Symbols {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut symbols: Vec<(&u16, &String)> = self.addresses.iter().map(|(name, addr)| (addr, name)).collect();
+        symbols.sort();
+
+        for (addr, name) in symbols {
+            writeln!(f, "0x{:04X} {}", addr, name)?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,116 @@
+use alloc::format;
+use alloc::string::String;
+use core::str::FromStr;
+
+use crate::instruction::Instruction;
+
+/*
+    How many instructions make up a frame. Most interpreters, and this one
+    by default, run a fixed number per 60 Hz tick. On the COSMAC VIP every
+    instruction was a routine of 1802 code that took its own time, and the
+    display's DMA and interrupt took a large share of each frame, so how
+    far a program got in a frame depended on what it was doing. A few games
+    were tuned to that pace.
+
+    The VIP figures below are the 1802 machine cycles the VIP interpreter
+    takes for each instruction: the fetch and dispatch every instruction
+    goes through, then its own routine. The routines with loops, 00E0,
+    Dxyn, Fx33, Fx55 and Fx65, take time in proportion to the work they do,
+    and Bnnn takes an extra instruction when nnn + V0 crosses a page. Dxyn
+    first waits for the display interrupt, so a sprite is always drawn at
+    the start of a frame.
+*/
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Timing {
+    // TICKS_PER_FRAME instructions, or whatever the frontend asks for
+    Fixed,
+    // Instructions run until the frame's machine cycles are used up
+    Vip,
+}
+
+impl Timing {
+    pub const NAMES: [&'static str; 2] = ["fixed", "vip"];
+}
+
+impl FromStr for Timing {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name.to_ascii_lowercase().as_str() {
+            "fixed" => Ok(Timing::Fixed),
+            "vip" | "cosmac" => Ok(Timing::Vip),
+            _ => Err(format!("unknown timing '{}', expected one of: {}", name, Self::NAMES.join(", "))),
+        }
+    }
+}
+
+// 1.76 MHz at 8 clocks per machine cycle, divided into 60 frames
+const FRAME_CYCLES: u32 = 3668;
+// Taken by the display DMA and the interrupt routine that drives it and the timers
+const DISPLAY_CYCLES: u32 = 1832;
+// Left for the interpreter each frame
+pub(crate) const CPU_CYCLES_PER_FRAME: u32 = FRAME_CYCLES - DISPLAY_CYCLES;
+
+// Fetching an instruction and jumping to its routine
+const DISPATCH: u32 = 40;
+// The extra increment of the program counter when a skip is taken
+pub(crate) const SKIP_CYCLES: u32 = 4;
+// Bnnn carrying into the high byte of the address
+const PAGE_CROSS: u32 = 2;
+// 00E0 clears 256 bytes of display memory one at a time
+const CLEAR_BYTE: u32 = 8;
+const DISPLAY_BYTES: u32 = 256;
+// Dxyn shifts each sprite row into place a bit at a time, then XORs one or two bytes
+const DRAW_SETUP: u32 = 46;
+const DRAW_ROW: u32 = 16;
+const DRAW_SHIFT: u32 = 4;
+// Fx33 finds each digit by repeated subtraction
+const BCD_SETUP: u32 = 48;
+const BCD_STEP: u32 = 16;
+// Fx55/Fx65 loop once per register
+const REGISTER_COPY: u32 = 14;
+
+// Cost of running `instruction` with the registers as they are before it
+pub(crate) fn vip_cycles(instruction: Instruction, v: &[u8; 16]) -> u32 {
+    let cycles = match instruction {
+        Instruction::Cls => 24 + CLEAR_BYTE * DISPLAY_BYTES,
+        Instruction::Ret => 10,
+        Instruction::Jump { .. } => 12,
+        Instruction::Call { .. } => 26,
+        Instruction::JumpV0 { nnn } if (nnn & 0xFF) + v[0] as u16 > 0xFF => 22 + PAGE_CROSS,
+        Instruction::JumpV0 { .. } => 22,
+        Instruction::SkipEqImm { .. } | Instruction::SkipNeImm { .. } => 10,
+        Instruction::SkipEqReg { .. } | Instruction::SkipNeReg { .. } => 14,
+        Instruction::LoadImm { .. } => 6,
+        Instruction::AddImm { .. } => 10,
+        Instruction::LoadIndex { .. } => 12,
+        // The VIP builds a tiny subroutine for the ALU operation and calls it
+        Instruction::LoadReg { .. }
+        | Instruction::Or { .. }
+        | Instruction::And { .. }
+        | Instruction::Xor { .. }
+        | Instruction::AddReg { .. }
+        | Instruction::SubReg { .. }
+        | Instruction::ShiftRight { .. }
+        | Instruction::SubNReg { .. }
+        | Instruction::ShiftLeft { .. } => 44,
+        Instruction::Random { .. } => 36,
+        Instruction::Draw { x, n, .. } => {
+            let shift = v[x] as u32 % 8;
+            DRAW_SETUP + n as u32 * (DRAW_ROW + DRAW_SHIFT * shift)
+        }
+        Instruction::SkipKeyPressed { .. } | Instruction::SkipKeyNotPressed { .. } => 14,
+        Instruction::AddIndex { .. } => 14,
+        Instruction::LoadFont { .. } => 16,
+        Instruction::StoreBcd { x } => {
+            let value = v[x] as u32;
+            BCD_SETUP + BCD_STEP * (value / 100 + value / 10 % 10 + value % 10)
+        }
+        Instruction::StoreRegs { x } | Instruction::LoadRegs { x } => REGISTER_COPY * (x as u32 + 1),
+        // Timer access, key waits, and the later extensions the VIP never had
+        _ => 10,
+    };
+
+    DISPATCH + cycles
+}
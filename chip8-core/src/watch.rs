@@ -0,0 +1,55 @@
+use core::fmt;
+
+use crate::instruction::Instruction;
+
+/*
+    Watchpoints stop on data accesses made by instructions (Fx33, Fx55,
+    Fx65, 5xy2, 5xy3, F002 and the sprite fetches of Dxyn) or when a
+    register changes value. Instruction fetches are not watched, use a
+    breakpoint for that.
+*/
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Watchpoint {
+    Read(u16),
+    Write(u16),
+    // VX changed value
+    Register(usize),
+    // I changed value
+    Index,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WatchHit {
+    pub watchpoint: Watchpoint,
+    // Address and decoded form of the instruction that triggered the watchpoint
+    pub addr: u16,
+    pub instruction: Instruction,
+    // For reads both hold the value read
+    pub old: u16,
+    pub new: u16,
+}
+
+impl fmt::Display for Watchpoint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Watchpoint::Read(addr) => write!(f, "R{:03X}", addr),
+            Watchpoint::Write(addr) => write!(f, "W{:03X}", addr),
+            Watchpoint::Register(x) => write!(f, "V{:X}", x),
+            Watchpoint::Index => write!(f, "I"),
+        }
+    }
+}
+
+impl fmt::Display for WatchHit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.watchpoint {
+            Watchpoint::Read(addr) => write!(f, "read {:02X} from {:03X}", self.new, addr)?,
+            Watchpoint::Write(addr) => write!(f, "write to {:03X}: {:02X} -> {:02X}", addr, self.old, self.new)?,
+            Watchpoint::Register(x) => write!(f, "V{:X} changed: {:02X} -> {:02X}", x, self.old, self.new)?,
+            Watchpoint::Index => write!(f, "I changed: {:03X} -> {:03X}", self.old, self.new)?,
+        }
+
+        write!(f, " by {} at {:03X}", self.instruction, self.addr)
+    }
+}
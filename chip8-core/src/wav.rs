@@ -0,0 +1,38 @@
+/*
+    Writes audio captures as 16-bit mono PCM WAV files, the format any
+    audio tool can open and compare.
+*/
+
+const BITS_PER_SAMPLE: u16 = 16;
+const CHANNELS: u16 = 1;
+// Format tag for integer PCM
+const FORMAT_PCM: u16 = 1;
+
+// `samples` are between -1 and 1, anything louder is clipped
+pub fn encode_wav(sample_rate: u32, samples: &[f32]) -> Vec<u8> {
+    let block_align = CHANNELS * BITS_PER_SAMPLE / 8;
+    let data_len = (samples.len() * block_align as usize) as u32;
+
+    let mut wav = Vec::with_capacity(44 + data_len as usize);
+    wav.extend(b"RIFF");
+    wav.extend((36 + data_len).to_le_bytes());
+    wav.extend(b"WAVE");
+
+    wav.extend(b"fmt ");
+    wav.extend(16u32.to_le_bytes());
+    wav.extend(FORMAT_PCM.to_le_bytes());
+    wav.extend(CHANNELS.to_le_bytes());
+    wav.extend(sample_rate.to_le_bytes());
+    wav.extend((sample_rate * block_align as u32).to_le_bytes());
+    wav.extend(block_align.to_le_bytes());
+    wav.extend(BITS_PER_SAMPLE.to_le_bytes());
+
+    wav.extend(b"data");
+    wav.extend(data_len.to_le_bytes());
+    for sample in samples {
+        let value = (sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16;
+        wav.extend(value.to_le_bytes());
+    }
+
+    wav
+}
@@ -0,0 +1,100 @@
+use chip8_core::asm::{assemble, assemble_file};
+
+use std::fs;
+use std::path::Path;
+
+fn rom(source: &str) -> Vec<u8> {
+    match assemble(source) {
+        Ok(assembly) => assembly.rom,
+        Err(e) => panic!("{}", e),
+    }
+}
+
+// The message of a program that must not assemble
+fn assemble_error(source: &str) -> String {
+    match assemble(source) {
+        Ok(_) => panic!("'{}' assembled", source),
+        Err(e) => e.to_string(),
+    }
+}
+
+#[test]
+fn instructions_and_labels() {
+    let source = "
+        SPEED = 4
+        loop:
+            ADD V0, SPEED
+            LD I, LONG far
+            JP loop
+        far:
+    ";
+    assert_eq!(rom(source), [0x70, 0x04, 0xF0, 0x00, 0x02, 0x08, 0x12, 0x00]);
+}
+
+#[test]
+fn operands_out_of_range() {
+    for (source, operand) in [
+        ("JP 0x1000", "0x1000"),
+        ("LD V0, 256", "256"),
+        ("LD V0, -129", "-129"),
+        ("DRW V0, V1, 16", "16"),
+        ("LD I, LONG 0x10000", "0x10000"),
+    ] {
+        let error = assemble_error(source);
+        assert!(error.contains(&format!("{} is out of range", operand)), "{}", error);
+    }
+
+    // Negative bytes wrap, so ADD V0, -1 subtracts one
+    assert_eq!(rom("ADD V0, -1"), [0x70, 0xFF]);
+}
+
+#[test]
+fn errors_name_the_line() {
+    assert_eq!(assemble_error("CLS\nLD V0, 300"), "<input>:2: 300 is out of range, must be between -128 and 255");
+    assert!(assemble_error("JP nowhere").contains("undefined symbol nowhere"));
+    assert!(assemble_error("LD VG, 1").contains("invalid operands for LD"));
+}
+
+#[test]
+fn data() {
+    let source = r#"
+        db 0x3C, 0b01111110, "hi", -1
+        dw 0x1234, table
+        table:
+        sprite "..XXXX..", "X......X"
+        sprite "XXXXXXXXXXXXXXXX"
+    "#;
+    assert_eq!(
+        rom(source),
+        [0x3C, 0x7E, b'h', b'i', 0xFF, 0x12, 0x34, 0x02, 0x09, 0x3C, 0x81, 0xFF, 0xFF]
+    );
+    assert_eq!(rom("sprite \"#......#\""), [0x81]);
+}
+
+#[test]
+fn bad_sprite_rows() {
+    assert!(assemble_error(r#"sprite "XXX""#).contains("8 or 16 pixels wide"));
+    assert!(assemble_error(r#"sprite "...o....""#).contains("invalid sprite pixel 'o'"));
+}
+
+#[test]
+fn include_is_relative_to_the_including_file() {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("asm_include");
+    fs::create_dir_all(dir.join("lib")).unwrap();
+    fs::write(dir.join("main.asm"), "CALL draw\ninclude \"lib/draw.asm\"\n").unwrap();
+    fs::write(dir.join("lib/draw.asm"), "draw:\n    include \"sprite.asm\"\n    RET\n").unwrap();
+    fs::write(dir.join("lib/sprite.asm"), "CLS\n").unwrap();
+
+    let assembly = assemble_file(&dir.join("main.asm")).unwrap();
+    assert_eq!(assembly.rom, [0x22, 0x02, 0x00, 0xE0, 0x00, 0xEE]);
+}
+
+#[test]
+fn include_loop() {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("asm_include_loop");
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("self.asm"), "include \"self.asm\"\n").unwrap();
+
+    let error = assemble_file(&dir.join("self.asm")).err().unwrap().to_string();
+    assert!(error.contains("includes nested too deeply"), "{}", error);
+}
@@ -0,0 +1,41 @@
+use chip8_core::asm::assemble;
+use chip8_core::disasm::Disassembly;
+use chip8_core::emulator::PROGRAM_START_ADDR;
+
+use std::fs;
+use std::path::Path;
+
+// Disassemble, assemble the listing again and expect the same bytes back
+fn round_trip(rom: &[u8]) {
+    let listing = Disassembly::new(rom, PROGRAM_START_ADDR as u16).listing();
+    match assemble(&listing) {
+        Ok(assembly) => assert_eq!(assembly.rom, rom, "listing assembled differently:\n{}", listing),
+        Err(e) => panic!("{}\n{}", e, listing),
+    }
+}
+
+#[test]
+fn targets_outside_the_rom() {
+    // LD I, 0x300 and JP 0x300
+    round_trip(&[0xA3, 0x00, 0x13, 0x00]);
+    // LD I, LONG 0x1234 then JP 0x200
+    round_trip(&[0xF0, 0x00, 0x12, 0x34, 0x12, 0x00]);
+}
+
+#[test]
+fn target_inside_an_instruction() {
+    // JP 0x203, the second byte of the jump itself
+    round_trip(&[0x12, 0x03]);
+}
+
+#[test]
+fn code_and_sprites() {
+    // LD I, sprite; DRW V0, V0, 2; JP 0x204; then the two sprite rows
+    round_trip(&[0xA2, 0x06, 0xD0, 0x02, 0x12, 0x04, 0x3C, 0x7E]);
+}
+
+#[test]
+fn invaders() {
+    let rom = fs::read(Path::new(env!("CARGO_MANIFEST_DIR")).join("../INVADERS")).unwrap();
+    round_trip(&rom);
+}
@@ -0,0 +1,89 @@
+use chip8_core::emulator::{Emulator, PROGRAM_START_ADDR};
+use chip8_core::quirks::Quirks;
+
+/*
+    XO-CHIP programs can fill all 64K of memory, so the program counter can
+    run right up to the last word and past it.
+*/
+
+const XO_MEMORY_SIZE: usize = 0x10000;
+
+// A ROM of 0000s, which do nothing, ending in `last` at 0xFFFE
+fn rom_ending_in(last: [u8; 2]) -> Vec<u8> {
+    let mut rom = vec![0; XO_MEMORY_SIZE - PROGRAM_START_ADDR];
+    let end = rom.len();
+    rom[end - 2..].copy_from_slice(&last);
+    rom
+}
+
+fn run_to_end(emu: &mut Emulator) {
+    for _ in 0..(XO_MEMORY_SIZE - PROGRAM_START_ADDR) / 2 {
+        emu.cycle().expect("instruction failed");
+    }
+}
+
+#[test]
+fn executes_the_last_word_of_memory() {
+    let mut emu = Emulator::new(Quirks::xochip());
+    // 6042: LD V0, 0x42
+    emu.load_rom(&rom_ending_in([0x60, 0x42])).unwrap();
+
+    run_to_end(&mut emu);
+
+    assert_eq!(emu.registers()[0], 0x42);
+    assert_eq!(emu.pc(), 0);
+}
+
+#[test]
+fn skips_from_the_last_word_of_memory() {
+    let mut emu = Emulator::new(Quirks::xochip());
+    // 3000: SE V0, 0, taken since V0 starts at 0. The next word is at 0
+    emu.load_rom(&rom_ending_in([0x30, 0x00])).unwrap();
+
+    run_to_end(&mut emu);
+
+    assert_eq!(emu.pc(), 2);
+}
+
+#[test]
+fn memory_follows_the_profile() {
+    assert_eq!(Emulator::new(Quirks::vip()).memory().len(), 0x1000);
+    assert_eq!(Emulator::new(Quirks::xochip()).memory().len(), XO_MEMORY_SIZE);
+}
+
+#[test]
+fn emulator_fits_on_a_small_stack() {
+    // RAM and the screen are on the heap, what is left is registers and bookkeeping
+    assert!(std::mem::size_of::<Emulator>() < 1024, "{} bytes", std::mem::size_of::<Emulator>());
+}
+
+// The screen after drawing the font's 8 at 10,10 and then running `scroll`
+fn scrolled(scroll: [u8; 2]) -> Vec<u8> {
+    // 600A A028 D005 then the scroll: V0 = 10, I = the 8, draw at V0, V0
+    let mut emu = Emulator::new(Quirks::schip());
+    emu.load_rom(&[0x60, 0x0A, 0xA0, 0x28, 0xD0, 0x05, scroll[0], scroll[1]]).unwrap();
+    for _ in 0..4 {
+        emu.cycle().unwrap();
+    }
+    emu.get_screen().to_vec()
+}
+
+fn moved(screen: &[u8], dx: isize, dy: isize) -> Vec<u8> {
+    let (width, height) = (64, 32);
+    (0..width * height)
+        .map(|i| {
+            let (x, y) = (i % width - dx, i / width - dy);
+            if (0..width).contains(&x) && (0..height).contains(&y) { screen[(x + y * width) as usize] } else { 0 }
+        })
+        .collect()
+}
+
+#[test]
+fn scrolls_in_place() {
+    let unscrolled = scrolled([0x00, 0x00]);
+    assert!(unscrolled.iter().any(|pixel| *pixel != 0));
+
+    assert_eq!(scrolled([0x00, 0xC3]), moved(&unscrolled, 0, 3));
+    assert_eq!(scrolled([0x00, 0xFB]), moved(&unscrolled, 4, 0));
+    assert_eq!(scrolled([0x00, 0xFC]), moved(&unscrolled, -4, 0));
+}
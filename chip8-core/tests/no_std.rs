@@ -0,0 +1,67 @@
+use std::env;
+use std::path::Path;
+use std::process::Command;
+
+/*
+    The interpreter has to keep building without std for microcontroller
+    hosts. These build the crate with --no-default-features, once for the
+    host, where #![no_std] still rejects any use of std, and once for a
+    bare-metal ARM target. That one needs the target's standard library,
+    so it only runs when asked for:
+
+        rustup target add thumbv7em-none-eabihf
+        cargo test -p chip8-core --test no_std -- --ignored
+*/
+
+const BARE_METAL_TARGET: &str = "thumbv7em-none-eabihf";
+
+fn build_without_std(target: Option<&str>) {
+    let manifest = Path::new(env!("CARGO_MANIFEST_DIR")).join("Cargo.toml");
+    // A separate target directory, the one running this test is locked
+    let target_dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("no_std");
+
+    let mut cargo = Command::new(env!("CARGO"));
+    cargo
+        .arg("build")
+        .arg("--lib")
+        .arg("--no-default-features")
+        .arg("--manifest-path")
+        .arg(&manifest)
+        .arg("--target-dir")
+        .arg(&target_dir);
+    if let Some(target) = target {
+        cargo.arg("--target").arg(target);
+    }
+
+    let output = cargo.output().expect("unable to run cargo");
+    assert!(output.status.success(), "no_std build failed:\n{}", String::from_utf8_lossy(&output.stderr));
+}
+
+fn target_installed(target: &str) -> bool {
+    let rustc = env::var("RUSTC").unwrap_or_else(|_| String::from("rustc"));
+    let output = match Command::new(rustc).args(["--print", "sysroot"]).output() {
+        Ok(output) if output.status.success() => output,
+        _ => return false,
+    };
+    let sysroot = String::from_utf8_lossy(&output.stdout);
+
+    Path::new(sysroot.trim()).join("lib/rustlib").join(target).exists()
+}
+
+#[test]
+fn builds_without_std() {
+    build_without_std(None);
+}
+
+#[test]
+#[ignore = "needs the thumbv7em-none-eabihf target, run with --ignored"]
+fn builds_for_bare_metal() {
+    assert!(
+        target_installed(BARE_METAL_TARGET),
+        "{} is not installed, run rustup target add {}",
+        BARE_METAL_TARGET,
+        BARE_METAL_TARGET
+    );
+
+    build_without_std(Some(BARE_METAL_TARGET));
+}
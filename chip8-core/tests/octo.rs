@@ -0,0 +1,35 @@
+use chip8_core::octo::compile;
+
+// The message of a program that must not compile
+fn compile_error(source: &str) -> String {
+    match compile(source) {
+        Ok(_) => panic!("'{}' compiled", source),
+        Err(e) => e.to_string(),
+    }
+}
+
+#[test]
+fn again_in_reach() {
+    let rom = compile(": main loop v0 += 1 again").expect("loop failed to compile").rom;
+    assert_eq!(rom, [0x12, 0x02, 0x70, 0x01, 0x12, 0x02]);
+}
+
+#[test]
+fn again_out_of_reach() {
+    // The loop starts past 0xFFF, where a plain jump can't go back to
+    let error = compile_error(": main :org 0x1000 loop v0 += 1 again");
+    assert!(error.contains("out of reach"), "{}", error);
+}
+
+#[test]
+fn calc_shift() {
+    assert!(compile(": main :calc x { 1 << 12 } :calc y { x >> 4 }").is_ok());
+}
+
+#[test]
+fn calc_shift_out_of_range() {
+    for source in [": main :calc x { 1 << 64 }", ": main :calc x { 1 << -1 }", ": main :calc x { 1 >> 64 }"] {
+        let error = compile_error(source);
+        assert!(error.contains("shift out of range"), "{}", error);
+    }
+}
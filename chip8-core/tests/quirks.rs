@@ -0,0 +1,35 @@
+use chip8_core::emulator::Emulator;
+use chip8_core::quirks::Quirks;
+
+// 6105 6208 8216: V1 = 5, V2 = 8, then SHR V2, V1
+const SHIFT: [u8; 6] = [0x61, 0x05, 0x62, 0x08, 0x82, 0x16];
+
+fn run(quirks: Quirks, rom: &[u8]) -> Emulator {
+    let mut emu = Emulator::new(quirks);
+    emu.load_rom(rom).unwrap();
+    for _ in 0..rom.len() / 2 {
+        emu.cycle().unwrap();
+    }
+    emu
+}
+
+#[test]
+fn default_shifts_vx_in_place() {
+    let emu = run(Quirks::default(), &SHIFT);
+    assert_eq!(emu.registers()[2], 4);
+    assert_eq!(emu.registers()[0xF], 0);
+}
+
+#[test]
+fn vip_shifts_vy() {
+    let emu = run(Quirks::vip(), &SHIFT);
+    assert_eq!(emu.registers()[2], 2);
+    assert_eq!(emu.registers()[0xF], 1);
+}
+
+#[test]
+fn default_leaves_i_after_fx55() {
+    // A300 F155: I = 300, then store V0 and V1
+    let emu = run(Quirks::default(), &[0xA3, 0x00, 0xF1, 0x55]);
+    assert_eq!(emu.index(), 0x300);
+}
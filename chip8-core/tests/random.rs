@@ -0,0 +1,49 @@
+use chip8_core::emulator::Emulator;
+use chip8_core::host::{RandomMode, RandomSource, VipRandom};
+use chip8_core::quirks::Quirks;
+
+#[test]
+fn vip_adds_the_code_byte_into_the_high_byte() {
+    let mut code = [0; 256];
+    code[0x35] = 0x10;
+    code[0x36] = 0x01;
+    let mut random = VipRandom::with_code(0x1234, code);
+
+    // R9 becomes 1235, then 2235 with the code byte at 35 added in
+    assert_eq!(random.random_byte(), 0x22);
+    assert_eq!(random.state(), 0x2235);
+    assert_eq!(random.random_byte(), 0x23);
+}
+
+#[test]
+fn vip_advances_with_the_display_interrupt() {
+    let code = core::array::from_fn(|low| low as u8);
+    let mut random = VipRandom::with_code(0, code);
+    random.tick();
+    random.tick();
+
+    // Low byte 3 after two interrupts and the call itself
+    assert_eq!(random.random_byte(), 3);
+}
+
+#[test]
+fn cxkk_masks_the_byte() {
+    let mut emu = Emulator::new(Quirks::vip());
+    emu.set_random_source(RandomMode::Vip.source(0x00FF));
+    // C00F: RND V0, 0x0F
+    emu.load_rom(&[0xC0, 0x0F]).unwrap();
+    emu.cycle().unwrap();
+
+    let mut random = VipRandom::new(0x00FF);
+    assert_eq!(emu.registers()[0], random.random_byte() & 0x0F);
+}
+
+#[test]
+fn seeded_sources_repeat() {
+    for mode in [RandomMode::Xorshift, RandomMode::Vip] {
+        let (mut a, mut b) = (mode.source(42), mode.source(42));
+        let a: Vec<u8> = (0..16).map(|_| a.random_byte()).collect();
+        let b: Vec<u8> = (0..16).map(|_| b.random_byte()).collect();
+        assert_eq!(a, b);
+    }
+}
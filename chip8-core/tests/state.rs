@@ -0,0 +1,88 @@
+use chip8_core::emulator::Emulator;
+use chip8_core::error::EmulatorError;
+use chip8_core::quirks::Quirks;
+
+use std::fs;
+use std::path::Path;
+
+// The format version follows the 4 byte magic
+const VERSION_OFFSET: usize = 4;
+
+fn invaders() -> Emulator {
+    let rom = fs::read(Path::new(env!("CARGO_MANIFEST_DIR")).join("../INVADERS")).unwrap();
+    let mut emu = Emulator::new(Quirks::default());
+    emu.load_rom(&rom).unwrap();
+    emu
+}
+
+fn run(emu: &mut Emulator, frames: usize) {
+    for _ in 0..frames {
+        emu.run_frame().unwrap();
+    }
+}
+
+#[test]
+fn round_trip() {
+    let mut emu = invaders();
+    run(&mut emu, 60);
+    let state = emu.save_state();
+
+    // Carry on, pressing a key so the game does something
+    emu.key_press(5, true);
+    run(&mut emu, 60);
+    let later = emu.save_state();
+
+    let mut restored = invaders();
+    restored.load_state(&state).unwrap();
+    assert_eq!(restored.save_state(), state);
+
+    // Random numbers included, the restored game plays out the same way
+    restored.key_press(5, true);
+    run(&mut restored, 60);
+    assert_eq!(restored.save_state(), later);
+    assert_eq!(restored.get_screen(), emu.get_screen());
+}
+
+#[test]
+fn rejects_other_versions() {
+    let mut emu = invaders();
+    run(&mut emu, 10);
+    let mut state = emu.save_state();
+    state[VERSION_OFFSET] = state[VERSION_OFFSET].wrapping_add(1);
+
+    let before = emu.save_state();
+    assert_eq!(emu.load_state(&state), Err(EmulatorError::InvalidState("unsupported save state version")));
+    assert_eq!(emu.save_state(), before);
+}
+
+#[test]
+fn rejects_damaged_states() {
+    let mut emu = invaders();
+    run(&mut emu, 10);
+    let state = emu.save_state();
+    let before = emu.save_state();
+
+    let mut foreign = state.clone();
+    foreign[0] = b'X';
+    assert_eq!(emu.load_state(&foreign), Err(EmulatorError::InvalidState("not a save state")));
+
+    assert_eq!(emu.load_state(&state[..state.len() - 1]), Err(EmulatorError::InvalidState("save state is truncated")));
+
+    let mut longer = state.clone();
+    longer.push(0);
+    assert_eq!(emu.load_state(&longer), Err(EmulatorError::InvalidState("save state has trailing data")));
+
+    // Nothing is touched by a state that fails to load
+    assert_eq!(emu.save_state(), before);
+}
+
+#[test]
+fn rejects_another_memory_size() {
+    let state = Emulator::new(Quirks::xochip()).save_state();
+    let mut emu = Emulator::new(Quirks::vip());
+
+    assert_eq!(
+        emu.load_state(&state),
+        Err(EmulatorError::InvalidState("memory size does not match the quirks profile"))
+    );
+}
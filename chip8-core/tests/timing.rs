@@ -0,0 +1,39 @@
+use chip8_core::emulator::Emulator;
+use chip8_core::quirks::Quirks;
+use chip8_core::timing::Timing;
+
+fn vip(rom: &[u8]) -> Emulator {
+    let mut emu = Emulator::new(Quirks::vip());
+    emu.set_timing(Timing::Vip);
+    emu.load_rom(rom).unwrap();
+    emu
+}
+
+#[test]
+fn frame_follows_machine_cycles() {
+    // 7001 1200: ADD V0, 1 and JP back, 50 + 52 cycles of the frame's 1836
+    let mut emu = vip(&[0x70, 0x01, 0x12, 0x00]);
+
+    emu.run_frame().unwrap();
+    assert_eq!(emu.registers()[0], 18);
+    emu.run_frame().unwrap();
+    assert_eq!(emu.registers()[0], 36);
+}
+
+#[test]
+fn draw_waits_for_the_display_interrupt() {
+    // D005 7101 1202: draw the 0 from the font, then count in V1
+    let mut emu = vip(&[0xD0, 0x05, 0x71, 0x01, 0x12, 0x02]);
+
+    // The first frame is spent waiting, with nothing drawn
+    emu.run_frame().unwrap();
+    assert!(emu.get_screen().iter().all(|pixel| *pixel == 0));
+    assert_eq!(emu.registers()[1], 0);
+    assert_eq!(emu.pc(), 0x200);
+
+    // The next one draws straight away and carries on
+    emu.run_frame().unwrap();
+    assert!(emu.get_screen().iter().any(|pixel| *pixel != 0));
+    assert!(emu.registers()[1] > 0);
+}
+
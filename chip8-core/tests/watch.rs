@@ -0,0 +1,89 @@
+use chip8_core::emulator::Emulator;
+use chip8_core::instruction::Instruction;
+use chip8_core::quirks::Quirks;
+use chip8_core::watch::{WatchHit, Watchpoint};
+
+/*
+    6042 A300 F055 F065 D011 7001 A302
+    V0 = 42, I = 300, store V0 at 300, load it back, draw the 1 byte sprite
+    at 300, add 1 to V0 and point I at 302
+*/
+const PROGRAM: [u8; 14] = [0x60, 0x42, 0xA3, 0x00, 0xF0, 0x55, 0xF0, 0x65, 0xD0, 0x11, 0x70, 0x01, 0xA3, 0x02];
+
+fn run(watchpoints: &[Watchpoint]) -> Vec<WatchHit> {
+    let mut emu = Emulator::new(Quirks::default());
+    emu.load_rom(&PROGRAM).unwrap();
+    for watchpoint in watchpoints {
+        emu.add_watchpoint(*watchpoint);
+    }
+    for _ in 0..PROGRAM.len() / 2 {
+        emu.cycle().unwrap();
+    }
+    emu.take_watch_hits()
+}
+
+#[test]
+fn write() {
+    let hits = run(&[Watchpoint::Write(0x300)]);
+
+    assert_eq!(
+        hits,
+        [WatchHit {
+            watchpoint: Watchpoint::Write(0x300),
+            addr: 0x204,
+            instruction: Instruction::StoreRegs { x: 0 },
+            old: 0,
+            new: 0x42,
+        }]
+    );
+    assert_eq!(hits[0].to_string(), "write to 300: 00 -> 42 by LD [I], V0 at 204");
+}
+
+#[test]
+fn reads_by_instructions_and_sprites() {
+    let hits = run(&[Watchpoint::Read(0x300)]);
+
+    let readers: Vec<u16> = hits.iter().map(|hit| hit.addr).collect();
+    assert_eq!(readers, [0x206, 0x208]);
+    assert!(hits.iter().all(|hit| hit.new == 0x42));
+}
+
+#[test]
+fn instruction_fetches_are_not_reads() {
+    assert!(run(&[Watchpoint::Read(0x200)]).is_empty());
+}
+
+#[test]
+fn registers() {
+    let hits = run(&[Watchpoint::Register(0), Watchpoint::Index]);
+
+    let changes: Vec<(Watchpoint, u16, u16)> = hits.iter().map(|hit| (hit.watchpoint, hit.old, hit.new)).collect();
+    assert_eq!(
+        changes,
+        [
+            (Watchpoint::Register(0), 0, 0x42),
+            (Watchpoint::Index, 0, 0x300),
+            (Watchpoint::Register(0), 0x42, 0x43),
+            (Watchpoint::Index, 0x300, 0x302),
+        ]
+    );
+}
+
+#[test]
+fn hits_are_taken_once() {
+    let mut emu = Emulator::new(Quirks::default());
+    emu.load_rom(&PROGRAM).unwrap();
+    emu.add_watchpoint(Watchpoint::Register(0));
+    emu.cycle().unwrap();
+
+    assert_eq!(emu.take_watch_hits().len(), 1);
+    assert!(emu.take_watch_hits().is_empty());
+
+    assert!(emu.remove_watchpoint(Watchpoint::Register(0)));
+    assert!(!emu.remove_watchpoint(Watchpoint::Register(0)));
+    // On past 7001, which changes V0 again
+    for _ in 1..PROGRAM.len() / 2 {
+        emu.cycle().unwrap();
+    }
+    assert!(emu.take_watch_hits().is_empty());
+}
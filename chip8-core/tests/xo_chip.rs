@@ -0,0 +1,32 @@
+use chip8_core::emulator::Emulator;
+use chip8_core::quirks::Quirks;
+
+fn run(quirks: Quirks, rom: &[u8], cycles: usize) -> Emulator {
+    let mut emu = Emulator::new(quirks);
+    emu.load_rom(rom).unwrap();
+    for _ in 0..cycles {
+        emu.cycle().unwrap();
+    }
+    emu
+}
+
+#[test]
+fn register_ranges_in_either_order() {
+    // V0-V2 = 1, 2, 3, I = 300, then 5202 stores V2 down to V0 and 5023 loads them back up
+    let rom = [0x60, 0x01, 0x61, 0x02, 0x62, 0x03, 0xA3, 0x00, 0x52, 0x02, 0x50, 0x23];
+
+    let emu = run(Quirks::xochip(), &rom, 5);
+    assert_eq!(&emu.memory()[0x300..0x303], [3, 2, 1]);
+
+    let emu = run(Quirks::xochip(), &rom, 6);
+    assert_eq!(&emu.registers()[..3], [3, 2, 1]);
+}
+
+#[test]
+fn skip_over_long_load_only_on_xo_chip() {
+    // 3000 F000 1234: SE V0, 0 is taken and skips whatever comes next
+    let rom = [0x30, 0x00, 0xF0, 0x00, 0x12, 0x34];
+
+    assert_eq!(run(Quirks::xochip(), &rom, 1).pc(), 0x206);
+    assert_eq!(run(Quirks::vip(), &rom, 1).pc(), 0x204);
+}
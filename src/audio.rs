@@ -0,0 +1,49 @@
+use chip8_core::sound::{Renderer, Tone, Wave};
+
+use sdl2::audio::AudioCallback;
+
+/*
+    Plays the buzzer on SDL's audio thread. The main loop hands it the
+    emulator's current tone once a frame through the device lock, and the
+    callback keeps playing that until told otherwise.
+*/
+
+pub struct Synth {
+    renderer: Renderer,
+    // 0 to 1
+    volume: f32,
+    muted: bool,
+}
+
+impl Synth {
+    pub fn new(wave: Wave, frequency: f32, volume: f32, sample_rate: i32) -> Self {
+        Self {
+            renderer: Renderer::new(sample_rate as u32, wave, frequency),
+            volume,
+            muted: false,
+        }
+    }
+
+    pub fn set_tone(&mut self, tone: Tone) {
+        self.renderer.set_tone(tone);
+    }
+
+    // Returns whether sound is now muted
+    pub fn toggle_mute(&mut self) -> bool {
+        self.muted = !self.muted;
+        self.muted
+    }
+}
+
+impl AudioCallback for Synth {
+    type Channel = f32;
+
+    fn callback(&mut self, out: &mut [f32]) {
+        self.renderer.render(out);
+
+        let volume = if self.muted { 0.0 } else { self.volume };
+        for sample in out.iter_mut() {
+            *sample *= volume;
+        }
+    }
+}
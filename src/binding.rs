@@ -0,0 +1,116 @@
+use crate::font::{draw_text, GLYPH_HEIGHT};
+use crate::keymap::{is_reserved, Input, Keymap, NUM_KEYS};
+
+use std::mem;
+
+use sdl2::keyboard::Keycode;
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+use sdl2::render::Canvas;
+use sdl2::video::Window;
+
+/*
+    The F10 screen for rebinding the keypad. It walks through the CHIP-8
+    keys in keypad order, every key or controller button pressed is added
+    to the current one and Return moves on to the next. Moving on without
+    pressing anything keeps the old binding. Escape leaves without
+    changing anything.
+*/
+
+const TEXT_SCALE: u32 = 2;
+const LINE_HEIGHT: i32 = ((GLYPH_HEIGHT + 2) * TEXT_SCALE) as i32;
+const MARGIN: i32 = 8;
+
+const BACKGROUND: Color = Color::RGB(24, 24, 32);
+const TEXT: Color = Color::RGB(200, 200, 200);
+const HIGHLIGHT: Color = Color::RGB(255, 210, 80);
+
+// Row by row, the way the keys are laid out on the keypad
+const KEYPAD_ORDER: [usize; NUM_KEYS] = [0x1, 0x2, 0x3, 0xC, 0x4, 0x5, 0x6, 0xD, 0x7, 0x8, 0x9, 0xE, 0xA, 0x0, 0xB, 0xF];
+
+pub enum Binding {
+    Pending,
+    Cancelled,
+    // The new layout is in keymap()
+    Finished,
+}
+
+pub struct BindingScreen {
+    keymap: Keymap,
+    // Position in KEYPAD_ORDER
+    position: usize,
+    pressed: Vec<Input>,
+    // Why the last key pressed was not added
+    refused: Option<String>,
+}
+
+impl BindingScreen {
+    pub fn new(keymap: &Keymap) -> Self {
+        Self { keymap: keymap.clone(), position: 0, pressed: Vec::new(), refused: None }
+    }
+
+    pub fn handle_key(&mut self, key: Keycode) -> Binding {
+        self.refused = None;
+
+        match key {
+            Keycode::Escape => return Binding::Cancelled,
+            Keycode::Return => {
+                if !self.pressed.is_empty() {
+                    let inputs = mem::take(&mut self.pressed);
+                    self.keymap.set(KEYPAD_ORDER[self.position], inputs);
+                }
+
+                self.position += 1;
+                if self.position == NUM_KEYS {
+                    return Binding::Finished;
+                }
+            }
+            _ if is_reserved(key) => self.refused = Some(format!("{} IS A HOTKEY, PICK ANOTHER KEY", key.name())),
+            _ => self.add(Input::Key(key)),
+        }
+
+        Binding::Pending
+    }
+
+    pub fn add(&mut self, input: Input) {
+        if !self.pressed.contains(&input) {
+            self.pressed.push(input);
+        }
+    }
+
+    pub fn keymap(&self) -> &Keymap {
+        &self.keymap
+    }
+
+    // Drawn over the game, which stays paused underneath
+    pub fn draw(&self, canvas: &mut Canvas<Window>, width: u32, height: u32) {
+        canvas.set_draw_color(BACKGROUND);
+        canvas.fill_rect(Rect::new(0, 0, width, height)).unwrap();
+
+        let mut y = MARGIN;
+        let mut line = |canvas: &mut Canvas<Window>, text: &str, color: Color| {
+            draw_text(canvas, MARGIN, y, text, TEXT_SCALE, color);
+            y += LINE_HEIGHT;
+        };
+
+        line(canvas, "PRESS KEYS OR BUTTONS, RETURN FOR NEXT, ESC TO CANCEL", HIGHLIGHT);
+
+        for (position, &key) in KEYPAD_ORDER.iter().enumerate() {
+            let (inputs, color) = if position == self.position && !self.pressed.is_empty() {
+                (self.pressed.as_slice(), HIGHLIGHT)
+            } else if position == self.position {
+                (self.keymap.inputs(key), HIGHLIGHT)
+            } else {
+                (self.keymap.inputs(key), TEXT)
+            };
+
+            let names: Vec<String> = inputs.iter().map(|input| input.to_string()).collect();
+            let marker = if position == self.position { '>' } else { ' ' };
+            line(canvas, &format!("{}{:X} = {}", marker, key, names.join(", ")), color);
+        }
+
+        if let Some(refused) = &self.refused {
+            line(canvas, refused, HIGHLIGHT);
+        }
+    }
+}
@@ -0,0 +1,78 @@
+use crate::keymap::Input;
+
+use sdl2::controller::GameController;
+use sdl2::event::Event;
+use sdl2::GameControllerSubsystem;
+
+/*
+    Game controllers, opened as they are plugged in. SDL reports the ones
+    already connected at startup as newly added too. Inputs from every
+    controller count the same, so two players can share the keypad.
+*/
+
+pub const DEFAULT_DEADZONE: u8 = 25;
+
+pub struct Controllers {
+    subsystem: GameControllerSubsystem,
+    open: Vec<GameController>,
+    // Stick positions closer to the centre than this count as released
+    deadzone: i16,
+    // Inputs held down, with the instance id of the controller holding them
+    held: Vec<(u32, Input)>,
+}
+
+impl Controllers {
+    // `deadzone` is a percentage of the stick's travel
+    pub fn new(subsystem: GameControllerSubsystem, deadzone: u8) -> Self {
+        Self {
+            subsystem,
+            open: Vec::new(),
+            deadzone: (i16::MAX as i32 * deadzone as i32 / 100) as i16,
+            held: Vec::new(),
+        }
+    }
+
+    // Returns false for events that are not about controllers
+    pub fn handle_event(&mut self, event: &Event) -> bool {
+        match *event {
+            Event::ControllerDeviceAdded { which, .. } => match self.subsystem.open(which) {
+                Ok(controller) => {
+                    println!("Controller connected: {}", controller.name());
+                    self.open.push(controller);
+                }
+                Err(e) => eprintln!("Unable to open controller {}: {}", which, e),
+            },
+            Event::ControllerDeviceRemoved { which, .. } => {
+                if let Some(at) = self.open.iter().position(|controller| controller.instance_id() == which) {
+                    println!("Controller disconnected: {}", self.open[at].name());
+                    self.open.remove(at);
+                }
+                self.held.retain(|(id, _)| *id != which);
+            }
+            Event::ControllerButtonDown { which, button, .. } => self.set(which, Input::Button(button), true),
+            Event::ControllerButtonUp { which, button, .. } => self.set(which, Input::Button(button), false),
+            Event::ControllerAxisMotion { which, axis, value, .. } => {
+                self.set(which, Input::Axis(axis, true), value > self.deadzone);
+                self.set(which, Input::Axis(axis, false), value < -self.deadzone);
+            }
+            _ => return false,
+        }
+
+        true
+    }
+
+    pub fn is_held(&self, input: Input) -> bool {
+        self.held.iter().any(|(_, held)| *held == input)
+    }
+
+    fn set(&mut self, id: u32, input: Input, pressed: bool) {
+        let entry = (id, input);
+        let held = self.held.contains(&entry);
+
+        if pressed && !held {
+            self.held.push(entry);
+        } else if !pressed && held {
+            self.held.retain(|other| *other != entry);
+        }
+    }
+}
@@ -0,0 +1,277 @@
+use std::collections::BTreeSet;
+
+use sdl2::keyboard::{Keycode, Mod};
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+use sdl2::render::Canvas;
+use sdl2::video::Window;
+
+use chip8_core::emulator::Emulator;
+use chip8_core::instruction::{decode, Instruction};
+use chip8_core::symbols::Symbols;
+use chip8_core::watch::Watchpoint;
+
+use crate::font::{draw_text, GLYPH_HEIGHT};
+
+/*
+    Debugger controls
+
+        F2          Pause / continue
+        F3          Step one instruction
+        F4          Step over: run a CALL until it returns to the next instruction
+        Shift+F4    Step out: run until the current subroutine returns
+        F8          Toggle a breakpoint on the current instruction
+        Shift+F8    Toggle a write watchpoint on the address in I
+*/
+
+pub const PANEL_WIDTH: u32 = 256;
+
+const TEXT_SCALE: u32 = 2;
+const LINE_HEIGHT: i32 = ((GLYPH_HEIGHT + 2) * TEXT_SCALE) as i32;
+const MARGIN: i32 = 8;
+// Instructions listed either side of the program counter
+const DISASSEMBLY_BEFORE: u16 = 3;
+const DISASSEMBLY_AFTER: u16 = 4;
+// Return addresses shown, most recent first
+const STACK_ENTRIES: usize = 6;
+
+const PANEL_BACKGROUND: Color = Color::RGB(24, 24, 32);
+const TEXT: Color = Color::RGB(200, 200, 200);
+const HIGHLIGHT: Color = Color::RGB(255, 210, 80);
+const BREAKPOINT: Color = Color::RGB(255, 90, 90);
+
+// Where to stop again after the user resumed with a step command
+#[derive(Clone, Copy)]
+enum RunUntil {
+    // Step over: back at this address with the stack as deep as when we started
+    Address { pc: u16, sp: u16 },
+    // Step out: the stack is shallower than this
+    Return { sp: u16 },
+}
+
+pub struct Debugger {
+    paused: bool,
+    breakpoints: BTreeSet<u16>,
+    run_until: Option<RunUntil>,
+    // Set when execution resumes so the breakpoint we are sitting on does not fire straight away
+    resuming: bool,
+    // Why we last stopped, shown at the top of the panel
+    status: String,
+    // Label names from the assembler, used in place of addresses
+    symbols: Symbols,
+}
+
+impl Debugger {
+    pub fn new(paused: bool, breakpoints: &[u16], symbols: Symbols) -> Self {
+        Self {
+            paused,
+            breakpoints: breakpoints.iter().copied().collect(),
+            run_until: None,
+            resuming: false,
+            status: String::from(if paused { "PAUSED" } else { "RUNNING" }),
+            symbols,
+        }
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    // Returns true if the key was a debugger command
+    pub fn handle_key(&mut self, key: Keycode, keymod: Mod, emu: &mut Emulator) -> bool {
+        let shift = keymod.intersects(Mod::LSHIFTMOD | Mod::RSHIFTMOD);
+
+        match key {
+            Keycode::F2 => {
+                if self.paused { self.resume(None) } else { self.pause("PAUSED") }
+            }
+            Keycode::F3 => self.step(emu),
+            Keycode::F4 if shift => {
+                // Outside of any subroutine there is nothing to step out of
+                if emu.sp() == 0 {
+                    self.step(emu);
+                } else {
+                    self.resume(Some(RunUntil::Return { sp: emu.sp() }));
+                }
+            }
+            Keycode::F4 => {
+                if let Some(Instruction::Call { .. }) = current_instruction(emu) {
+                    self.resume(Some(RunUntil::Address { pc: emu.pc().wrapping_add(2), sp: emu.sp() }));
+                } else {
+                    self.step(emu);
+                }
+            }
+            Keycode::F8 if shift => {
+                let watchpoint = Watchpoint::Write(emu.index());
+                if !emu.remove_watchpoint(watchpoint) {
+                    emu.add_watchpoint(watchpoint);
+                }
+            }
+            Keycode::F8 => {
+                let pc = emu.pc();
+                if !self.breakpoints.remove(&pc) {
+                    self.breakpoints.insert(pc);
+                }
+            }
+            _ => return false,
+        }
+
+        true
+    }
+
+    // Run up to `ticks` instructions, stopping early at breakpoints or when a step finishes.
+    // Unlike a normal run, errors pause the debugger so the state can still be inspected
+    pub fn run(&mut self, emu: &mut Emulator, ticks: usize) {
+        for _ in 0..ticks {
+            if self.paused || emu.frame_finished() {
+                return;
+            }
+
+            if !self.resuming && self.breakpoints.contains(&emu.pc()) {
+                self.pause(&format!("BREAK AT {}", self.address(emu.pc())));
+                return;
+            }
+            self.resuming = false;
+
+            if !self.cycle(emu) {
+                return;
+            }
+
+            let done = match self.run_until {
+                Some(RunUntil::Address { pc, sp }) => emu.pc() == pc && emu.sp() == sp,
+                Some(RunUntil::Return { sp }) => emu.sp() < sp,
+                None => false,
+            };
+            if done {
+                self.pause("STEPPED");
+            }
+        }
+    }
+
+    fn step(&mut self, emu: &mut Emulator) {
+        if self.cycle(emu) {
+            self.pause("STEPPED");
+        }
+    }
+
+    // Returns false if the instruction failed or hit a watchpoint, in which case we are now paused
+    fn cycle(&mut self, emu: &mut Emulator) -> bool {
+        if let Err(e) = emu.cycle() {
+            eprintln!("Error: {}", e);
+            self.pause("ERROR, SEE CONSOLE");
+            return false;
+        }
+
+        let hits = emu.take_watch_hits();
+        if let Some(first) = hits.first() {
+            for hit in &hits {
+                println!("Watchpoint: {}", hit);
+            }
+            self.pause(&format!("WATCH {} AT {:03X}", first.watchpoint, first.addr));
+            return false;
+        }
+
+        true
+    }
+
+    fn pause(&mut self, status: &str) {
+        self.paused = true;
+        self.run_until = None;
+        self.status = String::from(status);
+    }
+
+    fn resume(&mut self, until: Option<RunUntil>) {
+        self.paused = false;
+        self.run_until = until;
+        self.resuming = true;
+        self.status = String::from("RUNNING");
+    }
+
+    // Draw the machine state in a panel whose left edge is at `left`
+    pub fn draw(&self, emu: &Emulator, canvas: &mut Canvas<Window>, left: i32) {
+        let (_, height) = canvas.output_size().unwrap();
+        canvas.set_draw_color(PANEL_BACKGROUND);
+        canvas.fill_rect(Rect::new(left, 0, PANEL_WIDTH, height)).unwrap();
+
+        let x = left + MARGIN;
+        let mut y = MARGIN;
+        let mut line = |canvas: &mut Canvas<Window>, text: &str, color: Color| {
+            draw_text(canvas, x, y, text, TEXT_SCALE, color);
+            y += LINE_HEIGHT;
+        };
+
+        line(canvas, &self.status, HIGHLIGHT);
+        line(canvas, &format!("PC {:03X}  I {:03X}  SP {}", emu.pc(), emu.index(), emu.sp()), TEXT);
+        line(canvas, &format!("DT {:02X}  ST {:02X}", emu.delay_timer(), emu.sound_timer()), TEXT);
+
+        for (row, values) in emu.registers().chunks(4).enumerate() {
+            let text: Vec<String> = values
+                .iter()
+                .enumerate()
+                .map(|(i, value)| format!("V{:X} {:02X}", row * 4 + i, value))
+                .collect();
+            line(canvas, &text.join(" "), TEXT);
+        }
+
+        let stack: Vec<String> = emu.stack().iter().rev().take(STACK_ENTRIES).map(|addr| format!("{:03X}", addr)).collect();
+        line(canvas, &format!("STACK {}", stack.join(" ")), TEXT);
+
+        let watchpoints: Vec<String> = emu.watchpoints().iter().map(|w| w.to_string()).collect();
+        line(canvas, &format!("WATCH {}", watchpoints.join(" ")), TEXT);
+
+        // Assume instructions are aligned with the program counter
+        let pc = emu.pc();
+        let start = pc.saturating_sub(DISASSEMBLY_BEFORE * 2);
+        for addr in (start..=pc.saturating_add(DISASSEMBLY_AFTER * 2)).step_by(2) {
+            let marker = if addr == pc { '>' } else { ' ' };
+            let breakpoint = if self.breakpoints.contains(&addr) { '*' } else { ' ' };
+            let text = format!("{}{}{:03X} {}", breakpoint, marker, addr, self.disassemble(emu, addr));
+
+            let color = if addr == pc {
+                HIGHLIGHT
+            } else if breakpoint == '*' {
+                BREAKPOINT
+            } else {
+                TEXT
+            };
+            line(canvas, &text, color);
+        }
+    }
+
+    // An address by name if the symbol file has one
+    fn address(&self, addr: u16) -> String {
+        match self.symbols.name(addr) {
+            Some(name) => String::from(name),
+            None => format!("{:03X}", addr),
+        }
+    }
+
+    fn disassemble(&self, emu: &Emulator, addr: u16) -> String {
+        let op = match read_word(emu, addr) {
+            Some(op) => op,
+            None => return String::new(),
+        };
+
+        let target = |nnn: u16| self.symbols.name(nnn).map(String::from).unwrap_or_else(|| format!("0x{:03X}", nnn));
+        match decode(op) {
+            Ok(Instruction::Jump { nnn }) => format!("JP {}", target(nnn)),
+            Ok(Instruction::Call { nnn }) => format!("CALL {}", target(nnn)),
+            Ok(Instruction::JumpV0 { nnn }) => format!("JP V0, {}", target(nnn)),
+            Ok(Instruction::LoadIndex { nnn }) => format!("LD I, {}", target(nnn)),
+            Ok(instruction) => instruction.to_string(),
+            Err(_) => format!("DW 0x{:04X}", op),
+        }
+    }
+}
+
+fn read_word(emu: &Emulator, addr: u16) -> Option<u16> {
+    let memory = emu.memory();
+    let hi = *memory.get(addr as usize)? as u16;
+    let lo = *memory.get(addr as usize + 1)? as u16;
+
+    Some((hi << 8) | lo)
+}
+
+fn current_instruction(emu: &Emulator) -> Option<Instruction> {
+    read_word(emu, emu.pc()).and_then(|op| decode(op).ok())
+}
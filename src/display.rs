@@ -0,0 +1,133 @@
+use chip8_core::emulator::{SCREEN_HEIGHT, SCREEN_WIDTH};
+use chip8_core::filter::{Pixel, ScreenFilter};
+
+use std::str::FromStr;
+
+use sdl2::pixels::{Color, PixelFormatEnum};
+use sdl2::rect::Rect;
+use sdl2::render::{Canvas, Texture, TextureCreator};
+use sdl2::video::{Window, WindowContext};
+
+/*
+    Puts the CHIP-8 screen in the window. Each frame the filtered screen is
+    written into a streaming texture one texel per pixel, and the GPU
+    scales it up to fill as much of the window as the 2:1 shape allows,
+    with black bars around it. The texture is sized for SUPER-CHIP's
+    128x64 mode and low resolution only uses its top-left corner.
+*/
+
+pub const DEFAULT_SCALE: u32 = 8;
+const BYTES_PER_TEXEL: usize = 3;
+const LETTERBOX: Color = Color::RGB(0, 0, 0);
+
+// How the screen is scaled to the window
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fit {
+    // Whole multiples of the texture's 128x64, so every pixel is the same
+    // size. Low resolution pixels are then two by two of those
+    Integer,
+    // As large as fits
+    Aspect,
+}
+
+impl Fit {
+    pub const NAMES: [&'static str; 2] = ["integer", "aspect"];
+}
+
+impl FromStr for Fit {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name.to_ascii_lowercase().as_str() {
+            "integer" => Ok(Fit::Integer),
+            "aspect" => Ok(Fit::Aspect),
+            _ => Err(format!("unknown scaling '{}', expected one of: {}", name, Self::NAMES.join(", "))),
+        }
+    }
+}
+
+pub struct Display<'a> {
+    texture: Texture<'a>,
+    fit: Fit,
+    // Kept clear on the right of the window for the debugger
+    panel_width: u32,
+}
+
+impl<'a> Display<'a> {
+    pub fn new(texture_creator: &'a TextureCreator<WindowContext>, fit: Fit, panel_width: u32) -> Result<Self, String> {
+        let texture = texture_creator
+            .create_texture_streaming(PixelFormatEnum::RGB24, SCREEN_WIDTH as u32, SCREEN_HEIGHT as u32)
+            .map_err(|e| e.to_string())?;
+
+        Ok(Self { texture, fit, panel_width })
+    }
+
+    // The part of the window the game is shown in, left of the debugger panel
+    pub fn game_area(&self, canvas: &Canvas<Window>) -> (u32, u32) {
+        let (width, height) = canvas.output_size().unwrap();
+        (width.saturating_sub(self.panel_width), height)
+    }
+
+    pub fn draw(&mut self, canvas: &mut Canvas<Window>, filter: &ScreenFilter, colors: &[Color; 4]) -> Result<(), String> {
+        canvas.set_draw_color(LETTERBOX);
+        canvas.clear();
+
+        let width = filter.width();
+        if width == 0 {
+            return Ok(());
+        }
+        let height = filter.pixels().len() / width;
+
+        self.texture
+            .with_lock(Rect::new(0, 0, width as u32, height as u32), |buffer, pitch| {
+                for (y, row) in filter.pixels().chunks(width).enumerate() {
+                    let line = &mut buffer[y * pitch..y * pitch + width * BYTES_PER_TEXEL];
+                    for (texel, pixel) in line.chunks_mut(BYTES_PER_TEXEL).zip(row) {
+                        let color = pixel_color(*pixel, colors);
+                        texel.copy_from_slice(&[color.r, color.g, color.b]);
+                    }
+                }
+            })?;
+
+        let source = Rect::new(0, 0, width as u32, height as u32);
+        canvas.copy(&self.texture, source, self.viewport(canvas))
+    }
+
+    // Where the screen goes, centred in the game area
+    fn viewport(&self, canvas: &Canvas<Window>) -> Rect {
+        let (area_width, area_height) = self.game_area(canvas);
+        let (screen_width, screen_height) = (SCREEN_WIDTH as u32, SCREEN_HEIGHT as u32);
+
+        let scale = (area_width / screen_width).min(area_height / screen_height);
+
+        let (width, height) = match self.fit {
+            Fit::Integer if scale > 0 => (screen_width * scale, screen_height * scale),
+            // Integer falls back to this in a window smaller than the texture
+            Fit::Integer | Fit::Aspect => {
+                if area_width * screen_height > area_height * screen_width {
+                    (area_height * screen_width / screen_height, area_height)
+                } else {
+                    (area_width, area_width * screen_height / screen_width)
+                }
+            }
+        };
+
+        let x = (area_width as i32 - width as i32) / 2;
+        let y = (area_height as i32 - height as i32) / 2;
+        Rect::new(x, y, width.max(1), height.max(1))
+    }
+}
+
+// The palette colour for the planes lit, mixed with the background while it fades
+fn pixel_color(pixel: Pixel, colors: &[Color; 4]) -> Color {
+    let lit = colors[(pixel.planes & 0x3) as usize];
+    match pixel.level {
+        0 => colors[0],
+        255 => lit,
+        level => {
+            let mix = |background: u8, lit: u8| ((background as u32 * (255 - level) as u32 + lit as u32 * level as u32) / 255) as u8;
+            let background = colors[0];
+            Color::RGB(mix(background.r, lit.r), mix(background.g, lit.g), mix(background.b, lit.b))
+        }
+    }
+}
@@ -0,0 +1,295 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io::ErrorKind;
+
+use sdl2::controller::{Axis, Button};
+use sdl2::keyboard::Keycode;
+
+/*
+    Which host keys and controller buttons press which CHIP-8 keys. The
+    built-in layout is the left of a QWERTY keyboard, plus the D-pad or
+    left stick for 2/4/6/8 and the face buttons for 5, 0, A and B. A
+    keymap file changes any part of it:
+
+        # Used for every ROM
+        [default]
+        5 = W, Up
+        8 = S, Down
+
+        # Used for one ROM only, identified by the hash of its bytes
+        [rom 8e547ebb12c026b4]
+        name = INVADERS
+        4 = Left, pad:dpleft, pad:leftx-
+        6 = Right, pad:dpright, pad:leftx+
+
+    Each line lists everything that presses one CHIP-8 key. Keys go by
+    SDL's key names, controller buttons by SDL's button names after pad:,
+    and stick directions are an axis name followed by + or -. Keys a ROM's
+    section leaves out come from [default], and keys neither mentions keep
+    the built-in layout.
+*/
+
+pub const NUM_KEYS: usize = 16;
+pub const DEFAULT_KEYMAP_FILE: &str = "keymap.ini";
+
+/*
+    Keyboard                    Chip-8
+    +---+---+---+---+           +---+---+---+---+
+    | 1 | 2 | 3 | 4 |           | 1 | 2 | 3 | C |
+    +---+---+---+---+           +---+---+---+---+
+    | Q | W | E | R |           | 4 | 5 | 6 | D |
+    +---+---+---+---+     =>    +---+---+---+---+
+    | A | S | D | F |           | 7 | 8 | 9 | E |
+    +---+---+---+---+           +---+---+---+---+
+    | Z | X | C | V |           | A | 0 | B | F |
+    +---+---+---+---+           +---+---+---+---+
+*/
+
+// Indexed by CHIP-8 key
+const QWERTY: [Keycode; NUM_KEYS] = [
+    Keycode::X, Keycode::Num1, Keycode::Num2, Keycode::Num3,
+    Keycode::Q, Keycode::W, Keycode::E, Keycode::A,
+    Keycode::S, Keycode::D, Keycode::Z, Keycode::C,
+    Keycode::Num4, Keycode::R, Keycode::F, Keycode::V,
+];
+
+// Controller inputs in the built-in layout, on top of QWERTY
+const PAD: [(Input, usize); 12] = [
+    (Input::Button(Button::DPadUp), 0x2),
+    (Input::Button(Button::DPadLeft), 0x4),
+    (Input::Button(Button::DPadRight), 0x6),
+    (Input::Button(Button::DPadDown), 0x8),
+    (Input::Axis(Axis::LeftY, false), 0x2),
+    (Input::Axis(Axis::LeftX, false), 0x4),
+    (Input::Axis(Axis::LeftX, true), 0x6),
+    (Input::Axis(Axis::LeftY, true), 0x8),
+    (Input::Button(Button::A), 0x5),
+    (Input::Button(Button::B), 0x0),
+    (Input::Button(Button::X), 0xA),
+    (Input::Button(Button::Y), 0xB),
+];
+
+const PAD_PREFIX: &str = "pad:";
+
+// The emulator's hotkeys and the debugger's. They are handled before the
+// keymap is looked at, so they can't press CHIP-8 keys
+const RESERVED_KEYS: [Keycode; 18] = [
+    Keycode::Escape, Keycode::Backspace, Keycode::Tab, Keycode::M, Keycode::Minus, Keycode::Equals,
+    Keycode::F1, Keycode::F2, Keycode::F3, Keycode::F4, Keycode::F5, Keycode::F6,
+    Keycode::F7, Keycode::F8, Keycode::F9, Keycode::F10, Keycode::F11, Keycode::F12,
+];
+
+pub fn is_reserved(key: Keycode) -> bool {
+    RESERVED_KEYS.contains(&key)
+}
+
+// Something on the host that can be held down
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Input {
+    Key(Keycode),
+    Button(Button),
+    // A stick or trigger pushed past the deadzone, true for the positive direction
+    Axis(Axis, bool),
+}
+
+impl Input {
+    fn parse(name: &str) -> Result<Self, String> {
+        let pad = match name.strip_prefix(PAD_PREFIX) {
+            Some(pad) => pad,
+            None => {
+                return match Keycode::from_name(name) {
+                    Some(key) if is_reserved(key) => Err(format!("{} is a hotkey and can't be bound", name)),
+                    Some(key) => Ok(Input::Key(key)),
+                    None => Err(format!("unknown key '{}'", name)),
+                };
+            }
+        };
+
+        let axis = |direction| pad.strip_suffix(direction).and_then(Axis::from_string);
+        if let Some(axis) = axis('+') {
+            Ok(Input::Axis(axis, true))
+        } else if let Some(axis) = axis('-') {
+            Ok(Input::Axis(axis, false))
+        } else {
+            Button::from_string(pad).map(Input::Button).ok_or_else(|| format!("unknown controller input '{}'", name))
+        }
+    }
+}
+
+impl fmt::Display for Input {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Input::Key(key) => write!(f, "{}", key.name()),
+            Input::Button(button) => write!(f, "{}{}", PAD_PREFIX, button.string()),
+            Input::Axis(axis, positive) => write!(f, "{}{}{}", PAD_PREFIX, axis.string(), if *positive { '+' } else { '-' }),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Keymap {
+    inputs: [Vec<Input>; NUM_KEYS],
+}
+
+impl Keymap {
+    pub fn builtin() -> Self {
+        let mut inputs = QWERTY.map(|key| vec![Input::Key(key)]);
+        for (input, key) in PAD {
+            inputs[key].push(input);
+        }
+
+        Self { inputs }
+    }
+
+    pub fn inputs(&self, key: usize) -> &[Input] {
+        &self.inputs[key]
+    }
+
+    pub fn set(&mut self, key: usize, inputs: Vec<Input>) {
+        self.inputs[key] = inputs;
+    }
+}
+
+// One [section] of the file, with only the keys it mentions
+#[derive(Debug, Clone, Default)]
+struct Section {
+    name: Option<String>,
+    keys: BTreeMap<usize, Vec<Input>>,
+}
+
+impl Section {
+    fn apply(&self, keymap: &mut Keymap) {
+        for (key, inputs) in &self.keys {
+            keymap.set(*key, inputs.clone());
+        }
+    }
+}
+
+impl fmt::Display for Section {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(name) = &self.name {
+            writeln!(f, "name = {}", name)?;
+        }
+        for (key, inputs) in &self.keys {
+            let names: Vec<String> = inputs.iter().map(|input| input.to_string()).collect();
+            writeln!(f, "{:X} = {}", key, names.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct KeymapFile {
+    default: Section,
+    // Keyed by rom_hash
+    roms: BTreeMap<String, Section>,
+}
+
+impl KeymapFile {
+    // A file that doesn't exist yet is the same as an empty one
+    pub fn load(path: &str) -> Result<Self, String> {
+        match fs::read_to_string(path) {
+            Ok(text) => Self::parse(&text).map_err(|e| format!("{}: {}", path, e)),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(format!("Unable to open {}: {}", path, e)),
+        }
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut file = Self::default();
+        let mut section = None;
+
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            let error = |message: String| format!("line {}: {}", number + 1, message);
+
+            if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+                continue;
+            }
+
+            if let Some(name) = line.strip_prefix('[').and_then(|line| line.strip_suffix(']')) {
+                let name = name.trim();
+                section = match name.split_whitespace().collect::<Vec<_>>()[..] {
+                    ["default"] => None,
+                    ["rom", hash] => Some(hash.to_ascii_lowercase()),
+                    _ => return Err(error(format!("unknown section [{}]", name))),
+                };
+                if let Some(hash) = &section {
+                    file.roms.entry(hash.clone()).or_default();
+                }
+                continue;
+            }
+
+            let (name, value) = line.split_once('=').ok_or_else(|| error(format!("expected <key> = <inputs>, found '{}'", line)))?;
+            let (name, value) = (name.trim(), value.trim());
+            let target = match &section {
+                Some(hash) => file.roms.get_mut(hash).unwrap(),
+                None => &mut file.default,
+            };
+
+            if name == "name" {
+                target.name = Some(String::from(value));
+                continue;
+            }
+
+            let key = match usize::from_str_radix(name, 16) {
+                Ok(key) if key < NUM_KEYS && name.len() == 1 => key,
+                _ => return Err(error(format!("invalid CHIP-8 key '{}', expected 0-F", name))),
+            };
+            let inputs = value
+                .split(',')
+                .map(str::trim)
+                .filter(|name| !name.is_empty())
+                .map(Input::parse)
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(error)?;
+            target.keys.insert(key, inputs);
+        }
+
+        Ok(file)
+    }
+
+    // The layout for the ROM with this hash
+    pub fn keymap(&self, hash: &str) -> Keymap {
+        let mut keymap = Keymap::builtin();
+        self.default.apply(&mut keymap);
+        if let Some(section) = self.roms.get(hash) {
+            section.apply(&mut keymap);
+        }
+        keymap
+    }
+
+    // Remember a complete layout for one ROM
+    pub fn set_rom(&mut self, hash: &str, name: &str, keymap: &Keymap) {
+        let keys = (0..NUM_KEYS).map(|key| (key, keymap.inputs(key).to_vec())).collect();
+        self.roms.insert(String::from(hash), Section { name: Some(String::from(name)), keys });
+    }
+
+    // Comments in the file are not preserved
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        fs::write(path, self.to_string()).map_err(|e| format!("Unable to write {}: {}", path, e))
+    }
+}
+
+impl fmt::Display for KeymapFile {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "[default]")?;
+        write!(f, "{}", self.default)?;
+
+        for (hash, section) in &self.roms {
+            writeln!(f, "\n[rom {}]", hash)?;
+            write!(f, "{}", section)?;
+        }
+        Ok(())
+    }
+}
+
+// 64-bit FNV-1a of the ROM, as 16 hex digits. Identifies a game whatever its file is called
+pub fn rom_hash(rom: &[u8]) -> String {
+    const OFFSET_BASIS: u64 = 0xCBF2_9CE4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01B3;
+
+    let hash = rom.iter().fold(OFFSET_BASIS, |hash, byte| (hash ^ *byte as u64).wrapping_mul(PRIME));
+    format!("{:016x}", hash)
+}
@@ -1,134 +1,665 @@
-mod emulator;
-use emulator::*;
+mod audio;
+mod binding;
+mod controller;
+mod debugger;
+mod display;
+mod font;
+mod keymap;
+mod palette;
+mod rewind;
+mod scheduler;
+use chip8_core::emulator::*;
+use chip8_core::filter::{Filter, ScreenFilter};
+use chip8_core::host::{random_seed, RandomMode, StderrLog};
+use chip8_core::octo;
+use chip8_core::quirks::Quirks;
+use chip8_core::sound::{Tone, Wave, DEFAULT_FREQUENCY};
+use chip8_core::symbols::Symbols;
+use chip8_core::timing::Timing;
+use chip8_core::watch::Watchpoint;
+use audio::Synth;
+use binding::{Binding, BindingScreen};
+use controller::{Controllers, DEFAULT_DEADZONE};
+use debugger::{Debugger, PANEL_WIDTH};
+use display::{Display, Fit, DEFAULT_SCALE};
+use keymap::{rom_hash, Input, Keymap, KeymapFile, DEFAULT_KEYMAP_FILE, NUM_KEYS};
+use palette::Palette;
+use rewind::Rewind;
+use scheduler::{Scheduler, DEFAULT_IPS};
 
-use std::fs::File;
-use std::io::Read;
+use std::fs;
 use std::env;
+use std::path::Path;
+use std::process;
 
+use sdl2::audio::AudioSpecDesired;
 use sdl2::event::Event;
 use sdl2::keyboard::Keycode;
+use sdl2::messagebox::{show_simple_message_box, MessageBoxFlag};
 use sdl2::pixels::Color;
-use sdl2::rect::Rect;
 use sdl2::render::Canvas;
-use sdl2::video::Window;
+use sdl2::video::{FullscreenType, Window};
 
-const SCALE: u32 = 8;
-const WINDOW_WIDTH: u32 = (SCREEN_WIDTH as u32) * SCALE;
-const WINDOW_HEIGHT: u32 = (SCREEN_HEIGHT as u32) * SCALE;
-const TICKS_PER_FRAME: usize = 10;
+const FRAMES_PER_SECOND: usize = 60;
+const NUM_STATE_SLOTS: u32 = 10;
+const DEFAULT_REWIND_SECONDS: usize = 10;
+const DEFAULT_VOLUME: u8 = 25;
+const SAMPLE_RATE: i32 = 44100;
+
+const USAGE: &str = "Usage: cargo run [options] path/to/game
+
+Octo source files (.8o) are compiled and run directly.
+
+Options:
+    --quirks <profile>    modern, vip, chip48, schip or xochip (default modern)
+    --ips <number>        instructions per second (default 600)
+    --timing <mode>       fixed, the --ips rate, or vip for the COSMAC VIP's instruction timings (default fixed)
+    --seed <number>       seed for Cxnn's random numbers, for reproducible runs
+    --random <mode>       xorshift or vip, the COSMAC VIP's generator (default xorshift)
+    --tone-freq <hz>      pitch of the buzzer (default 440)
+    --volume <percent>    0 to 100 (default 25)
+    --wave <shape>        square, sine or triangle (default square)
+    --scale <number>      starting window size in window pixels per low resolution pixel (default 8)
+    --fit <mode>          integer keeps every pixel the same size, aspect fills as much of the
+                          window as the screen's shape allows (default aspect)
+    --palette <colours>   classic, amber, green, lcd, or hex colours for the background and
+                          planes 1, 2 and both, like 000000,ffffff,aaaaaa,555555 (default classic)
+    --filter <mode>       reduce flicker: or shows pixels lit in either of the last two frames,
+                          decay or decay:<frames> fades them out (default none, decay is 4 frames)
+    --rewind <seconds>    history kept for rewinding with Backspace, 0 to disable (default 10)
+    --debug               start paused with the debugger panel open
+    --break <address>     set a breakpoint at a hex address or symbol, implies --debug
+    --keymap <file>       key bindings, see the README (default keymap.ini if it exists)
+    --deadzone <percent>  how far a controller stick has to move to press a key (default 25)
+    --symbols <file>      label names from chip8-asm --symbols, shown in the debugger
+    --watch <what>        break on r:<address> reads, w:<address> writes, rw:<address>,
+                          changes to a register (v0-vf) or to i, implies --debug";
+
+struct Options {
+    rom: String,
+    quirks: Quirks,
+    ips: u32,
+    timing: Timing,
+    seed: Option<u64>,
+    random: RandomMode,
+    tone_frequency: f32,
+    volume: u8,
+    wave: Wave,
+    scale: u32,
+    fit: Fit,
+    palette: Palette,
+    filter: Filter,
+    rewind_seconds: usize,
+    debug: bool,
+    // Addresses or symbol names, resolved once the symbols are loaded
+    breakpoints: Vec<String>,
+    watchpoints: Vec<Watchpoint>,
+    symbols: Option<String>,
+    keymap: Option<String>,
+    deadzone: u8,
+}
+
+fn parse_args() -> Result<Options, String> {
+    let mut rom = None;
+    let mut quirks = Quirks::default();
+    let mut ips = DEFAULT_IPS;
+    let mut timing = Timing::Fixed;
+    let mut seed = None;
+    let mut random = RandomMode::Xorshift;
+    let mut tone_frequency = DEFAULT_FREQUENCY;
+    let mut volume = DEFAULT_VOLUME;
+    let mut wave = Wave::Square;
+    let mut scale = DEFAULT_SCALE;
+    let mut fit = Fit::Aspect;
+    let mut palette = Palette::default();
+    let mut filter = Filter::None;
+    let mut rewind_seconds = DEFAULT_REWIND_SECONDS;
+    let mut debug = false;
+    let mut breakpoints = Vec::new();
+    let mut watchpoints = Vec::new();
+    let mut symbols = None;
+    let mut keymap = None;
+    let mut deadzone = DEFAULT_DEADZONE;
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--quirks" => {
+                let name = args.next().ok_or("--quirks needs a profile name")?;
+                quirks = name.parse()?;
+            }
+            "--ips" => {
+                let number = args.next().ok_or("--ips needs a number")?;
+                ips = match number.parse() {
+                    Ok(ips) if ips > 0 => ips,
+                    _ => return Err(format!("invalid instructions per second {}", number)),
+                };
+            }
+            "--timing" => {
+                let name = args.next().ok_or("--timing needs a mode")?;
+                timing = name.parse()?;
+            }
+            "--seed" => {
+                let number = args.next().ok_or("--seed needs a number")?;
+                seed = Some(parse_seed(&number)?);
+            }
+            "--random" => {
+                let name = args.next().ok_or("--random needs a mode")?;
+                random = name.parse()?;
+            }
+            "--tone-freq" => {
+                let hz = args.next().ok_or("--tone-freq needs a frequency")?;
+                tone_frequency = match hz.parse() {
+                    Ok(hz) if hz > 0.0 => hz,
+                    _ => return Err(format!("invalid tone frequency {}", hz)),
+                };
+            }
+            "--volume" => {
+                let percent = args.next().ok_or("--volume needs a percentage")?;
+                volume = match percent.parse() {
+                    Ok(percent) if percent <= 100 => percent,
+                    _ => return Err(format!("invalid volume {}", percent)),
+                };
+            }
+            "--wave" => {
+                let name = args.next().ok_or("--wave needs a waveform")?;
+                wave = name.parse()?;
+            }
+            "--scale" => {
+                let number = args.next().ok_or("--scale needs a number")?;
+                scale = match number.parse() {
+                    Ok(scale) if scale > 0 => scale,
+                    _ => return Err(format!("invalid scale {}", number)),
+                };
+            }
+            "--fit" => {
+                let mode = args.next().ok_or("--fit needs a mode")?;
+                fit = mode.parse()?;
+            }
+            "--palette" => {
+                let colours = args.next().ok_or("--palette needs a name or colours")?;
+                palette = colours.parse()?;
+            }
+            "--filter" => {
+                let mode = args.next().ok_or("--filter needs a mode")?;
+                filter = mode.parse()?;
+            }
+            "--rewind" => {
+                let seconds = args.next().ok_or("--rewind needs a number of seconds")?;
+                rewind_seconds = seconds.parse().map_err(|_| format!("invalid rewind length {}", seconds))?;
+            }
+            "--debug" => debug = true,
+            "--break" => {
+                breakpoints.push(args.next().ok_or("--break needs an address")?);
+                debug = true;
+            }
+            "--watch" => {
+                let spec = args.next().ok_or("--watch needs something to watch")?;
+                watchpoints.extend(parse_watchpoint(&spec)?);
+                debug = true;
+            }
+            "--symbols" => symbols = Some(args.next().ok_or("--symbols needs a file name")?),
+            "--keymap" => keymap = Some(args.next().ok_or("--keymap needs a file name")?),
+            "--deadzone" => {
+                let percent = args.next().ok_or("--deadzone needs a percentage")?;
+                deadzone = match percent.parse() {
+                    Ok(percent) if percent < 100 => percent,
+                    _ => return Err(format!("invalid deadzone {}", percent)),
+                };
+            }
+            _ if arg.starts_with("--") => return Err(format!("unknown option {}", arg)),
+            _ if rom.is_none() => rom = Some(arg),
+            _ => return Err(String::from("only one ROM can be given")),
+        }
+    }
+
+    let rom = rom.ok_or("no ROM given")?;
+
+    Ok(Options {
+        rom,
+        quirks,
+        ips,
+        timing,
+        seed,
+        random,
+        tone_frequency,
+        volume,
+        wave,
+        scale,
+        fit,
+        palette,
+        filter,
+        rewind_seconds,
+        debug,
+        breakpoints,
+        watchpoints,
+        symbols,
+        keymap,
+        deadzone,
+    })
+}
+
+fn parse_watchpoint(spec: &str) -> Result<Vec<Watchpoint>, String> {
+    let spec = spec.to_ascii_lowercase();
+
+    if spec == "i" {
+        return Ok(vec![Watchpoint::Index]);
+    }
+    if let Some(reg) = spec.strip_prefix('v') {
+        return match usize::from_str_radix(reg, 16) {
+            Ok(x) if x < 16 => Ok(vec![Watchpoint::Register(x)]),
+            _ => Err(format!("invalid register {}", spec)),
+        };
+    }
+
+    match spec.split_once(':') {
+        Some(("r", addr)) => Ok(vec![Watchpoint::Read(parse_address(addr)?)]),
+        Some(("w", addr)) => Ok(vec![Watchpoint::Write(parse_address(addr)?)]),
+        Some(("rw", addr)) => {
+            let addr = parse_address(addr)?;
+            Ok(vec![Watchpoint::Read(addr), Watchpoint::Write(addr)])
+        }
+        _ => Err(format!("invalid watchpoint {}", spec)),
+    }
+}
+
+// Addresses are hex, with or without a leading 0x
+fn parse_address(text: &str) -> Result<u16, String> {
+    let digits = text.trim_start_matches("0x").trim_start_matches("0X");
+    u16::from_str_radix(digits, 16).map_err(|_| format!("invalid address {}", text))
+}
+
+// Decimal, or hex with a leading 0x
+fn parse_seed(text: &str) -> Result<u64, String> {
+    let seed = match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        Some(hex) => u64::from_str_radix(hex, 16),
+        None => text.parse(),
+    };
+    seed.map_err(|_| format!("invalid seed {}", text))
+}
 
 fn main() {
-    let args: Vec<_> = env::args().collect();
-    if args.len() != 2 {
-        println!("Usage: cargo run path/to/game");
-        return;
+    let options = match parse_args() {
+        Ok(options) => options,
+        Err(e) => {
+            println!("{}\n{}", e, USAGE);
+            return;
+        }
+    };
+
+    if let Err(e) = run(&options) {
+        eprintln!("Error: {}", e);
+        process::exit(1);
     }
+}
+
+fn run(options: &Options) -> Result<(), String> {
+    let path = &options.rom;
+    let (rom, compiled_symbols) = if path.ends_with(".8o") {
+        let assembly = octo::compile_file(Path::new(path)).map_err(|e| e.to_string())?;
+        (assembly.rom, assembly.symbols)
+    } else {
+        let rom = fs::read(path).map_err(|e| format!("Unable to open {}: {}", path, e))?;
+        (rom, Symbols::new())
+    };
+
+    // Octo labels are used unless a symbol file is given
+    let symbols = match &options.symbols {
+        Some(path) => {
+            let text = fs::read_to_string(path).map_err(|e| format!("Unable to open {}: {}", path, e))?;
+            Symbols::parse(&text).map_err(|e| format!("{}: {}", path, e))?
+        }
+        None => compiled_symbols,
+    };
+    let breakpoints = options
+        .breakpoints
+        .iter()
+        .map(|name| symbols.address(name).map_or_else(|| parse_address(name), Ok))
+        .collect::<Result<Vec<u16>, String>>()?;
+
+    // Bindings changed with F10 are saved back to the same file
+    let keymap_path = options.keymap.as_deref().unwrap_or(DEFAULT_KEYMAP_FILE);
+    let mut keymap_file = KeymapFile::load(keymap_path)?;
+    let hash = rom_hash(&rom);
+    let mut keymap = keymap_file.keymap(&hash);
+    let rom_name = Path::new(path).file_name().map_or_else(|| path.clone(), |name| name.to_string_lossy().into_owned());
 
     // Setup SDL
-    let sdl_context = sdl2::init().unwrap();
-    let video_subsystem = sdl_context.video().unwrap();
+    let sdl_context = sdl2::init()?;
+    let video_subsystem = sdl_context.video()?;
+    // The debugger panel sits to the right of the game
+    let panel_width = if options.debug { PANEL_WIDTH } else { 0 };
     let window = video_subsystem
-        .window("Chip-8 Emulator", WINDOW_WIDTH, WINDOW_HEIGHT)
+        .window("Chip-8 Emulator", LORES_WIDTH as u32 * options.scale + panel_width, LORES_HEIGHT as u32 * options.scale)
         .position_centered()
+        .resizable()
         .opengl()
         .build()
-        .unwrap();
+        .map_err(|e| e.to_string())?;
 
-    let mut canvas = window.into_canvas().present_vsync().build().unwrap();
+    let mut canvas = window.into_canvas().present_vsync().build().map_err(|e| e.to_string())?;
+    canvas.window_mut().set_minimum_size(LORES_WIDTH as u32 + panel_width, LORES_HEIGHT as u32).map_err(|e| e.to_string())?;
     canvas.clear();
     canvas.present();
 
-    let mut event_pump = sdl_context.event_pump().unwrap();
+    let texture_creator = canvas.texture_creator();
+    let mut display = Display::new(&texture_creator, options.fit, panel_width)?;
+
+    let mut event_pump = sdl_context.event_pump()?;
+
+    // Controllers are optional too, the keyboard always works
+    let mut controllers = match sdl_context.game_controller() {
+        Ok(subsystem) => Some(Controllers::new(subsystem, options.deadzone)),
+        Err(e) => {
+            eprintln!("Unable to use game controllers: {}", e);
+            None
+        }
+    };
+    // Host keys currently down, turned into CHIP-8 keys through the keymap
+    let mut held_keys = Vec::new();
+
+    // A missing or busy sound card shouldn't stop anyone playing, so carry on silently
+    let audio = sdl_context.audio().and_then(|audio| {
+        let desired = AudioSpecDesired { freq: Some(SAMPLE_RATE), channels: Some(1), samples: None };
+        audio.open_playback(None, &desired, |spec| {
+            Synth::new(options.wave, options.tone_frequency, options.volume as f32 / 100.0, spec.freq)
+        })
+    });
+    let mut audio = match audio {
+        Ok(device) => {
+            device.resume();
+            Some(device)
+        }
+        Err(e) => {
+            eprintln!("Unable to open audio, continuing without sound: {}", e);
+            None
+        }
+    };
+
+    let mut chip8 = Emulator::new(options.quirks);
+    chip8.set_log(Box::new(StderrLog));
+    chip8.set_timing(options.timing);
+    let seed = options.seed.unwrap_or_else(random_seed);
+    chip8.set_random_source(options.random.source(seed));
+
+    chip8.load_rom(&rom).map_err(|e| e.to_string())?;
+    for watchpoint in &options.watchpoints {
+        chip8.add_watchpoint(*watchpoint);
+    }
+
+    // Save state slot used by F5/F9, changed with F6/F7
+    let mut slot = 0;
+
+    // Recent frames to play back while Backspace is held
+    let mut rewind = Rewind::new(options.rewind_seconds * FRAMES_PER_SECOND);
+    let mut rewinding = false;
 
-    let mut chip8 = Emulator::new();
+    let mut scheduler = Scheduler::new(options.ips);
 
-    let mut rom = File::open(&args[1]).expect("Unable to open file");
-    let mut buffer = Vec::new();
+    let mut debugger = if options.debug { Some(Debugger::new(true, &breakpoints, symbols)) } else { None };
 
-    rom.read_to_end(&mut buffer).unwrap();
-    chip8.load_rom(&buffer);
+    // F12 cycles through the themes, starting from the chosen palette
+    let mut palettes = Palette::themes();
+    let mut palette = match palettes.iter().position(|palette| *palette == options.palette) {
+        Some(index) => index,
+        None => {
+            palettes.push(options.palette.clone());
+            palettes.len() - 1
+        }
+    };
+
+    // What is drawn is the screen after flicker filtering
+    let mut filter = ScreenFilter::new(options.filter);
+
+    // Open while F10 rebinding is in progress, the game is paused meanwhile
+    let mut binding: Option<BindingScreen> = None;
 
     'gameloop: loop {
         for evt in event_pump.poll_iter() {
+            if let Some(screen) = binding.as_mut() {
+                match evt {
+                    Event::Quit{..} => break 'gameloop,
+                    Event::ControllerButtonDown{button, ..} => screen.add(Input::Button(button)),
+                    Event::KeyUp{keycode: Some(key), ..} => held_keys.retain(|held| *held != key),
+                    Event::KeyDown{keycode: Some(key), repeat: false, ..} => match screen.handle_key(key) {
+                        Binding::Pending => (),
+                        Binding::Cancelled => binding = None,
+                        Binding::Finished => {
+                            keymap = screen.keymap().clone();
+                            keymap_file.set_rom(&hash, &rom_name, &keymap);
+                            match keymap_file.save(keymap_path) {
+                                Ok(()) => println!("Saved key bindings for {} to {}", rom_name, keymap_path),
+                                Err(e) => eprintln!("{}", e),
+                            }
+                            binding = None;
+                        }
+                    },
+                    _ => (),
+                }
+                if let Some(controllers) = controllers.as_mut() {
+                    controllers.handle_event(&evt);
+                }
+                continue;
+            }
+
+            if let Some(controllers) = controllers.as_mut() {
+                if controllers.handle_event(&evt) {
+                    continue;
+                }
+            }
+
             match evt {
                 Event::Quit{..} | Event::KeyDown{keycode: Some(Keycode::Escape), ..}=> {
                     break 'gameloop;
                 },
-                Event::KeyDown{keycode: Some(key), ..} => {
-                    if let Some(k) = key2btn(key) {
-                        chip8.key_press(k, true);
+                Event::KeyDown{keycode: Some(Keycode::F5), repeat: false, ..} => {
+                    save_state(&chip8, path, slot);
+                },
+                Event::KeyDown{keycode: Some(Keycode::F9), repeat: false, ..} => {
+                    load_state(&mut chip8, path, slot);
+                },
+                Event::KeyDown{keycode: Some(Keycode::F6), ..} => {
+                    slot = (slot + NUM_STATE_SLOTS - 1) % NUM_STATE_SLOTS;
+                    println!("Save state slot {}", slot);
+                },
+                Event::KeyDown{keycode: Some(Keycode::F7), ..} => {
+                    slot = (slot + 1) % NUM_STATE_SLOTS;
+                    println!("Save state slot {}", slot);
+                },
+                Event::KeyDown{keycode: Some(Keycode::Backspace), ..} => {
+                    rewinding = true;
+                },
+                Event::KeyUp{keycode: Some(Keycode::Backspace), ..} => {
+                    rewinding = false;
+                },
+                Event::KeyDown{keycode: Some(Keycode::F10), repeat: false, ..} => {
+                    binding = Some(BindingScreen::new(&keymap));
+                },
+                Event::KeyDown{keycode: Some(Keycode::F11), repeat: false, ..} => {
+                    let window = canvas.window_mut();
+                    let fullscreen = match window.fullscreen_state() {
+                        FullscreenType::Off => FullscreenType::Desktop,
+                        _ => FullscreenType::Off,
+                    };
+                    window.set_fullscreen(fullscreen)?;
+                },
+                Event::KeyDown{keycode: Some(Keycode::F12), repeat: false, ..} => {
+                    palette = (palette + 1) % palettes.len();
+                    println!("Palette {}", palettes[palette].name());
+                },
+                Event::KeyDown{keycode: Some(Keycode::Minus), repeat: false, ..} => {
+                    println!("Speed {}x", scheduler.slower());
+                },
+                Event::KeyDown{keycode: Some(Keycode::Equals), repeat: false, ..} => {
+                    println!("Speed {}x", scheduler.faster());
+                },
+                Event::KeyDown{keycode: Some(Keycode::Tab), ..} => {
+                    scheduler.set_fast_forward(true);
+                },
+                Event::KeyUp{keycode: Some(Keycode::Tab), ..} => {
+                    scheduler.set_fast_forward(false);
+                },
+                Event::KeyDown{keycode: Some(Keycode::M), repeat: false, ..} => {
+                    if let Some(audio) = audio.as_mut() {
+                        let muted = audio.lock().toggle_mute();
+                        println!("Sound {}", if muted { "muted" } else { "on" });
                     }
                 },
-                Event::KeyUp{keycode: Some(key), ..} => {
-                    if let Some(k) = key2btn(key) {
-                        chip8.key_press(k, false);
+                Event::KeyDown{keycode: Some(key), keymod, ..} => {
+                    if let Some(debugger) = debugger.as_mut() {
+                        if debugger.handle_key(key, keymod, &mut chip8) {
+                            continue;
+                        }
+                    }
+                    if !held_keys.contains(&key) {
+                        held_keys.push(key);
                     }
                 },
+                Event::KeyUp{keycode: Some(key), ..} => {
+                    held_keys.retain(|held| *held != key);
+                },
                 _ => ()
             }
         }
 
-        for _ in 0..TICKS_PER_FRAME {
-            chip8.cycle();
+        update_keys(&mut chip8, &keymap, &held_keys, controllers.as_ref());
+
+        // The buzzer only sounds while the game is actually running
+        let paused = debugger.as_ref().is_some_and(|d| d.is_paused());
+        if let Some(audio) = audio.as_mut() {
+            audio.lock().set_tone(if rewinding || paused || binding.is_some() { Tone::Silent } else { chip8.tone() });
+        }
+
+        if let Some(screen) = &binding {
+            scheduler.skip();
+            display.draw(&mut canvas, &filter, palettes[palette].colors())?;
+            let (width, height) = display.game_area(&canvas);
+            screen.draw(&mut canvas, width, height);
+            canvas.present();
+            continue;
+        }
+
+        if rewinding {
+            // A saved frame for every frame that would have run, so the game plays
+            // backwards at the speed it plays forwards. Stay on the oldest frame
+            // once history runs out
+            for _ in 0..scheduler.frames() {
+                if let Some(state) = rewind.pop() {
+                    chip8.load_state(state).map_err(|e| e.to_string())?;
+                }
+                filter.push(&chip8);
+            }
+            present(&chip8, &mut display, &filter, palettes[palette].colors(), debugger.as_ref(), &mut canvas)?;
+            continue;
+        }
+
+        // Each frame is the instructions that fit in a 60th of a second, then a timer tick
+        for _ in 0..scheduler.frames() {
+            // With VIP timing the emulator decides when the frame is over
+            let cycles = match options.timing {
+                Timing::Fixed => scheduler.cycles(),
+                Timing::Vip => usize::MAX,
+            };
+            match debugger.as_mut() {
+                Some(debugger) => debugger.run(&mut chip8, cycles),
+                None => {
+                    for _ in 0..cycles {
+                        if chip8.frame_finished() {
+                            break;
+                        }
+                        if let Err(e) = chip8.cycle() {
+                            // Let the user see what went wrong before the window disappears
+                            let message = format!("The emulator stopped: {}", e);
+                            show_simple_message_box(MessageBoxFlag::ERROR, "Chip-8 Emulator", &message, canvas.window())
+                                .map_err(|e| e.to_string())?;
+                            return Err(e.to_string());
+                        }
+                    }
+                }
+            }
+
+            // Time stands still while the debugger has the machine paused
+            if debugger.as_ref().is_some_and(|d| d.is_paused()) {
+                scheduler.skip();
+                break;
+            }
+            chip8.increment_timers();
+            rewind.push(chip8.save_state());
+            filter.push(&chip8);
+
+            if chip8.is_halted() {
+                break;
+            }
+        }
+        // Let the filter settle on the screen as it is, so steps in the debugger show up
+        if paused {
+            filter.push(&chip8);
+        }
+        present(&chip8, &mut display, &filter, palettes[palette].colors(), debugger.as_ref(), &mut canvas)?;
+
+        // The ROM asked to quit with 00FD
+        if chip8.is_halted() {
+            break 'gameloop;
         }
-        chip8.increment_timers();
-        draw_screen(&chip8, &mut canvas);
     }
+
+    Ok(())
 }
 
-fn draw_screen(emu: &Emulator, canvas: &mut Canvas<Window>) {
-    // Clear canvas as black
-    canvas.set_draw_color(Color::RGB(0, 0, 0));
-    canvas.clear();
+// Save states are kept next to the ROM, one file per slot
+fn state_path(rom: &str, slot: u32) -> String {
+    format!("{}.state{}", rom, slot)
+}
 
-    let screen_buf = emu.get_screen();
-    // Now set draw color to white, iterate through each point and see if it should be drawn
-    canvas.set_draw_color(Color::RGB(255, 255, 255));
-    for (i, pixel) in screen_buf.iter().enumerate() {
-        if *pixel {
-            // Convert our 1D array's index into a 2D (x,y) position
-            let x = (i % SCREEN_WIDTH) as u32;
-            let y = (i / SCREEN_WIDTH) as u32;
-
-            // Draw a rectangle at (x,y), scaled up by our SCALE value
-            let rect = Rect::new((x * SCALE) as i32, (y * SCALE) as i32, SCALE, SCALE);
-            canvas.fill_rect(rect).unwrap();
-        }
+fn save_state(emu: &Emulator, rom: &str, slot: u32) {
+    let path = state_path(rom, slot);
+
+    match fs::write(&path, emu.save_state()) {
+        Ok(()) => println!("Saved state to {}", path),
+        Err(e) => eprintln!("Unable to save state to {}: {}", path, e),
+    }
+}
+
+fn load_state(emu: &mut Emulator, rom: &str, slot: u32) {
+    let path = state_path(rom, slot);
+
+    let result = fs::read(&path)
+        .map_err(|e| e.to_string())
+        .and_then(|data| emu.load_state(&data).map_err(|e| e.to_string()));
+
+    match result {
+        Ok(()) => println!("Loaded state from {}", path),
+        Err(e) => eprintln!("Unable to load state from {}: {}", path, e),
+    }
+}
+
+// Press every CHIP-8 key that something held down is bound to and release
+// the rest. Snapshots remember which keys were down when they were taken,
+// this also puts those right after loading one
+fn update_keys(emu: &mut Emulator, keymap: &Keymap, held_keys: &[Keycode], controllers: Option<&Controllers>) {
+    for key in 0..NUM_KEYS {
+        let pressed = keymap.inputs(key).iter().any(|input| match input {
+            Input::Key(keycode) => held_keys.contains(keycode),
+            _ => controllers.is_some_and(|controllers| controllers.is_held(*input)),
+        });
+        emu.key_press(key, pressed);
     }
-    canvas.present();
 }
 
-/*
-    Keyboard                    Chip-8
-    +---+---+---+---+           +---+---+---+---+
-    | 1 | 2 | 3 | 4 |           | 1 | 2 | 3 | C |
-    +---+---+---+---+           +---+---+---+---+
-    | Q | W | E | R |           | 4 | 5 | 6 | D |
-    +---+---+---+---+     =>    +---+---+---+---+
-    | A | S | D | F |           | 7 | 8 | 9 | E |
-    +---+---+---+---+           +---+---+---+---+
-    | Z | X | C | V |           | A | 0 | B | F |
-    +---+---+---+---+           +---+---+---+---+
-*/
-
-fn key2btn(key: Keycode) -> Option<usize> {
-    match key {
-        Keycode::Num1 =>    Some(0x1),
-        Keycode::Num2 =>    Some(0x2),
-        Keycode::Num3 =>    Some(0x3),
-        Keycode::Num4 =>    Some(0xC),
-        Keycode::Q =>       Some(0x4),
-        Keycode::W =>       Some(0x5),
-        Keycode::E =>       Some(0x6),
-        Keycode::R =>       Some(0xD),
-        Keycode::A =>       Some(0x7),
-        Keycode::S =>       Some(0x8),
-        Keycode::D =>       Some(0x9),
-        Keycode::F =>       Some(0xE),
-        Keycode::Z =>       Some(0xA),
-        Keycode::X =>       Some(0x0),
-        Keycode::C =>       Some(0xB),
-        Keycode::V =>       Some(0xF),
-        _ =>                None,
+fn present(
+    emu: &Emulator,
+    display: &mut Display,
+    filter: &ScreenFilter,
+    colors: &[Color; 4],
+    debugger: Option<&Debugger>,
+    canvas: &mut Canvas<Window>,
+) -> Result<(), String> {
+    display.draw(canvas, filter, colors)?;
+    if let Some(debugger) = debugger {
+        let (width, _) = display.game_area(canvas);
+        debugger.draw(emu, canvas, width as i32);
     }
-}
\ No newline at end of file
+    canvas.present();
+
+    Ok(())
+}
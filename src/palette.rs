@@ -0,0 +1,89 @@
+use std::str::FromStr;
+
+use sdl2::pixels::Color;
+
+/*
+    Screen colours. Each palette has a colour per combination of XO-CHIP
+    bitplanes: the background, plane 1, plane 2 and both planes. Plain
+    CHIP-8 only ever uses the first two.
+*/
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Palette {
+    name: String,
+    colors: [Color; 4],
+}
+
+const THEMES: [(&str, [Color; 4]); 4] = [
+    ("classic", [Color::RGB(0, 0, 0), Color::RGB(255, 255, 255), Color::RGB(170, 170, 170), Color::RGB(85, 85, 85)]),
+    ("amber", [Color::RGB(20, 12, 0), Color::RGB(255, 176, 0), Color::RGB(170, 100, 0), Color::RGB(255, 220, 130)]),
+    ("green", [Color::RGB(0, 18, 6), Color::RGB(60, 255, 110), Color::RGB(20, 150, 60), Color::RGB(180, 255, 200)]),
+    ("lcd", [Color::RGB(155, 188, 15), Color::RGB(15, 56, 15), Color::RGB(48, 98, 48), Color::RGB(100, 140, 30)]),
+];
+
+impl Palette {
+    pub const NAMES: [&'static str; 4] = ["classic", "amber", "green", "lcd"];
+
+    // The built-in palettes, in the order F12 goes through them
+    pub fn themes() -> Vec<Palette> {
+        THEMES.iter().map(|(name, colors)| Palette { name: String::from(*name), colors: *colors }).collect()
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    // Indexed by a pixel's plane mask
+    pub fn colors(&self) -> &[Color; 4] {
+        &self.colors
+    }
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Self::themes().swap_remove(0)
+    }
+}
+
+// A theme by name, or two to four hex colours separated by commas for the
+// background, plane 1, plane 2 and both planes. Missing plane colours are
+// the same as plane 1
+impl FromStr for Palette {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let name = text.to_ascii_lowercase();
+        if let Some(theme) = Self::themes().into_iter().find(|theme| theme.name == name) {
+            return Ok(theme);
+        }
+
+        if !text.contains(',') {
+            return Err(format!(
+                "unknown palette '{}', expected one of: {} or a list of hex colours",
+                text,
+                Self::NAMES.join(", ")
+            ));
+        }
+
+        let colors = text.split(',').map(parse_color).collect::<Result<Vec<Color>, String>>()?;
+        if colors.len() > 4 {
+            return Err(format!("too many colours in palette '{}', expected at most 4", text));
+        }
+
+        let mut palette = [colors[0], colors[1], colors[1], colors[1]];
+        palette[..colors.len()].copy_from_slice(&colors);
+
+        Ok(Palette { name: String::from("custom"), colors: palette })
+    }
+}
+
+// RRGGBB with an optional leading #
+fn parse_color(text: &str) -> Result<Color, String> {
+    let text = text.trim();
+    let digits = text.strip_prefix('#').unwrap_or(text);
+
+    match u32::from_str_radix(digits, 16) {
+        Ok(rgb) if digits.len() == 6 => Ok(Color::RGB((rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8)),
+        _ => Err(format!("invalid colour '{}', expected RRGGBB", text)),
+    }
+}
@@ -0,0 +1,213 @@
+use std::collections::VecDeque;
+
+/*
+    Ring buffer of recent save states for playing a game backwards.
+
+    Only the newest snapshot is kept whole. Every older frame is stored as
+    the XOR of it and the frame after it, run-length encoded. Consecutive
+    frames differ in a handful of registers and screen pixels, so most of
+    the XOR is zeros and a frame costs a few dozen bytes instead of a full
+    copy of RAM and the screen.
+*/
+
+pub struct Rewind {
+    latest: Vec<u8>,
+    // Back of the queue is the delta from `latest` to the frame before it
+    deltas: VecDeque<Vec<u8>>,
+    capacity: usize,
+}
+
+impl Rewind {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            latest: Vec::new(),
+            deltas: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    pub fn push(&mut self, state: Vec<u8>) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        // Snapshots only change size if the emulator was reconfigured, in which case history is useless
+        if self.latest.len() != state.len() {
+            self.deltas.clear();
+        } else {
+            self.deltas.push_back(encode_delta(&state, &self.latest));
+            if self.deltas.len() > self.capacity {
+                self.deltas.pop_front();
+            }
+        }
+
+        self.latest = state;
+    }
+
+    // Step one frame back in time and return the snapshot for that frame
+    pub fn pop(&mut self) -> Option<&[u8]> {
+        let delta = self.deltas.pop_back()?;
+        apply_delta(&mut self.latest, &delta);
+
+        Some(&self.latest)
+    }
+}
+
+/*
+    A delta is a list of (zero run, literal run) pairs. Both lengths are
+    LEB128 varints and each literal run is followed by its XORed bytes.
+*/
+
+fn encode_delta(new: &[u8], old: &[u8]) -> Vec<u8> {
+    let mut out = Vec::new();
+    let mut i = 0;
+
+    while i < new.len() {
+        let zeros_start = i;
+        while i < new.len() && new[i] == old[i] {
+            i += 1;
+        }
+
+        let literal_start = i;
+        while i < new.len() && new[i] != old[i] {
+            i += 1;
+        }
+
+        write_varint(&mut out, literal_start - zeros_start);
+        write_varint(&mut out, i - literal_start);
+        out.extend((literal_start..i).map(|j| new[j] ^ old[j]));
+    }
+
+    out
+}
+
+fn apply_delta(state: &mut [u8], delta: &[u8]) {
+    let mut pos = 0;
+    let mut i = 0;
+
+    while i < delta.len() {
+        pos += read_varint(delta, &mut i);
+        let literals = read_varint(delta, &mut i);
+
+        for byte in &delta[i..i + literals] {
+            state[pos] ^= byte;
+            pos += 1;
+        }
+        i += literals;
+    }
+}
+
+fn write_varint(out: &mut Vec<u8>, mut value: usize) {
+    while value >= 0x80 {
+        out.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn read_varint(data: &[u8], i: &mut usize) -> usize {
+    let mut value = 0;
+    let mut shift = 0;
+
+    loop {
+        let byte = data[*i];
+        *i += 1;
+        value |= ((byte & 0x7F) as usize) << shift;
+        if byte & 0x80 == 0 {
+            return value;
+        }
+        shift += 7;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Frames as the emulator makes them: mostly unchanged, a few bytes apart
+    fn frames() -> Vec<Vec<u8>> {
+        let mut frame: Vec<u8> = (0..300).map(|i| (i * 7) as u8).collect();
+        let mut frames = vec![frame.clone()];
+        for n in 1..20 {
+            frame[n * 13 % 300] ^= n as u8;
+            frame[299] = frame[299].wrapping_add(1);
+            frame[0..(n % 4)].fill(n as u8);
+            frames.push(frame.clone());
+        }
+        frames
+    }
+
+    #[test]
+    fn delta_round_trip() {
+        let frames = frames();
+        for pair in frames.windows(2) {
+            let (old, new) = (&pair[0], &pair[1]);
+            let delta = encode_delta(new, old);
+            assert!(delta.len() < new.len() / 4, "{} byte delta", delta.len());
+
+            let mut state = new.clone();
+            apply_delta(&mut state, &delta);
+            assert_eq!(&state, old);
+        }
+    }
+
+    #[test]
+    fn delta_of_identical_and_different_states() {
+        let old = vec![1; 1000];
+        assert_eq!(encode_delta(&old, &old), [0xE8, 0x07, 0x00]);
+
+        let new: Vec<u8> = (0..1000).map(|i| i as u8 ^ 1).collect();
+        let mut state = new.clone();
+        apply_delta(&mut state, &encode_delta(&new, &old));
+        assert_eq!(state, old);
+    }
+
+    #[test]
+    fn varints() {
+        for value in [0, 1, 0x7F, 0x80, 0x3FFF, 0x4000, 1 << 40] {
+            let mut out = Vec::new();
+            write_varint(&mut out, value);
+            let mut i = 0;
+            assert_eq!(read_varint(&out, &mut i), value);
+            assert_eq!(i, out.len());
+        }
+    }
+
+    #[test]
+    fn plays_back_in_reverse() {
+        let frames = frames();
+        let mut rewind = Rewind::new(frames.len());
+        for frame in &frames {
+            rewind.push(frame.clone());
+        }
+
+        for frame in frames.iter().rev().skip(1) {
+            assert_eq!(rewind.pop(), Some(frame.as_slice()));
+        }
+        assert_eq!(rewind.pop(), None);
+    }
+
+    #[test]
+    fn keeps_only_capacity_frames() {
+        let frames = frames();
+        let mut rewind = Rewind::new(5);
+        for frame in &frames {
+            rewind.push(frame.clone());
+        }
+
+        for _ in 0..5 {
+            assert!(rewind.pop().is_some());
+        }
+        assert_eq!(rewind.pop(), None);
+    }
+
+    #[test]
+    fn forgets_states_of_another_size() {
+        let mut rewind = Rewind::new(10);
+        rewind.push(vec![1; 10]);
+        rewind.push(vec![2; 10]);
+        rewind.push(vec![3; 20]);
+
+        assert_eq!(rewind.pop(), None);
+    }
+}
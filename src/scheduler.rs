@@ -0,0 +1,93 @@
+use std::time::{Duration, Instant};
+
+/*
+    Decides how much emulation is due each time round the main loop, from
+    the wall clock rather than from how often the display refreshes. Work
+    is handed out in whole 60 Hz frames, each one the instructions that
+    fit in a 60th of a second at the current speed followed by one timer
+    tick, so the timers run at exactly 60 Hz on any monitor and a frame
+    always ends where the screen, rewind history and timers expect it to.
+*/
+
+pub const DEFAULT_IPS: u32 = 600;
+
+// Speeds reachable with - and =, relative to the configured rate
+const SPEEDS: [f64; 6] = [0.25, 0.5, 1.0, 2.0, 4.0, 8.0];
+const NORMAL_SPEED: usize = 2;
+// How much faster than the current speed Tab runs while held
+const FAST_FORWARD: f64 = 8.0;
+// Falling further behind than this, after a stall or while the window is
+// being dragged, drops the backlog instead of racing to catch up
+const MAX_BACKLOG: Duration = Duration::from_millis(250);
+const FRAMES_PER_SECOND: f64 = 60.0;
+
+pub struct Scheduler {
+    ips: u32,
+    speed: usize,
+    fast_forward: bool,
+    last: Instant,
+    // Emulated time owed but not yet run, in 60 Hz frames
+    frames_due: f64,
+    // Fraction of an instruction left over from the last frame
+    cycles_due: f64,
+}
+
+impl Scheduler {
+    pub fn new(ips: u32) -> Self {
+        Self {
+            ips,
+            speed: NORMAL_SPEED,
+            fast_forward: false,
+            last: Instant::now(),
+            frames_due: 0.0,
+            cycles_due: 0.0,
+        }
+    }
+
+    // Number of 60 Hz frames to run now
+    pub fn frames(&mut self) -> usize {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last).min(MAX_BACKLOG);
+        self.last = now;
+
+        self.frames_due += elapsed.as_secs_f64() * FRAMES_PER_SECOND * self.multiplier();
+        let frames = self.frames_due as usize;
+        self.frames_due -= frames as f64;
+        frames
+    }
+
+    // Instructions to run in the next frame. Rates that aren't a multiple
+    // of 60 carry the remainder over so the average comes out exact
+    pub fn cycles(&mut self) -> usize {
+        self.cycles_due += self.ips as f64 / FRAMES_PER_SECOND;
+        let cycles = self.cycles_due as usize;
+        self.cycles_due -= cycles as f64;
+        cycles
+    }
+
+    // Forget the time spent paused or rewinding, so it isn't made up afterwards
+    pub fn skip(&mut self) {
+        self.last = Instant::now();
+        self.frames_due = 0.0;
+    }
+
+    // Return the new speed multiplier
+    pub fn faster(&mut self) -> f64 {
+        self.speed = (self.speed + 1).min(SPEEDS.len() - 1);
+        SPEEDS[self.speed]
+    }
+
+    pub fn slower(&mut self) -> f64 {
+        self.speed = self.speed.saturating_sub(1);
+        SPEEDS[self.speed]
+    }
+
+    pub fn set_fast_forward(&mut self, fast_forward: bool) {
+        self.fast_forward = fast_forward;
+    }
+
+    fn multiplier(&self) -> f64 {
+        let speed = SPEEDS[self.speed];
+        if self.fast_forward { speed * FAST_FORWARD } else { speed }
+    }
+}